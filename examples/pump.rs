@@ -4,6 +4,7 @@ use anyhow::Result;
 use raytx::{
     get_rpc_client,
     pump::{get_bonding_curve_account, get_pda, PUMP_PROGRAM},
+    token,
 };
 use solana_sdk::pubkey::Pubkey;
 #[tokio::main]
@@ -24,8 +25,9 @@ pub async fn get_bonding_curve_by_mint() -> Result<()> {
     let bonding_curve = get_pda(&mint, &program_id)?;
     println!("bonding_curve: {bonding_curve}");
 
+    let token_program = token::get_mint_program(client.clone(), &mint).await?;
     let bonding_curve_account =
-        get_bonding_curve_account(client, &mint, &Pubkey::from_str(PUMP_PROGRAM)?).await;
+        get_bonding_curve_account(client, &mint, &token_program, &program_id).await;
 
     println!("bonding_curve_account: {:#?}", bonding_curve_account);
     Ok(())
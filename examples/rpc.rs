@@ -4,8 +4,10 @@ use amm_cli::load_amm_keys;
 use anyhow::{Context, Result};
 use common::common_utils;
 use futures_util::{SinkExt, StreamExt};
-use raytx::{get_rpc_client, logger, pump::PUMP_PROGRAM, raydium::get_pool_state_by_mint};
-use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use raytx::{
+    get_random_rpc_url, get_rpc_client, logger, pump::PUMP_PROGRAM, raydium::get_pool_state_by_mint,
+};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tracing::{error, info};
@@ -33,7 +35,8 @@ pub async fn get_amm_info_by_mint() -> Result<()> {
 }
 
 pub async fn get_amm_info() -> Result<()> {
-    let client = get_rpc_client()?;
+    // raydium-library reads through the blocking client
+    let client = RpcClient::new(get_random_rpc_url()?);
     // let amm_pool_id = Pubkey::from_str("3vehHGc8J9doSo6gJoWYG23JG54hc2i7wjdFReX3Rcah")?;
     let amm_pool_id = Pubkey::from_str("7Sp76Pv48RaL4he2BfGUhvjqCtvjjfTSnXDXNvk845yL")?;
 
@@ -103,7 +106,9 @@ pub async fn get_signatures() -> Result<()> {
     };
 
     let address = Pubkey::from_str(PUMP_PROGRAM)?;
-    let signatures = client.get_signatures_for_address_with_config(&address, config)?;
+    let signatures = client
+        .get_signatures_for_address_with_config(&address, config)
+        .await?;
 
     for signature in signatures {
         info!("{:#?}", signature);
@@ -0,0 +1,198 @@
+use std::fmt;
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+/// Basis points in a whole balance.
+pub const MAX_BPS: u64 = 10_000;
+/// Fractional digits of a share of a balance, one basis point.
+const BPS_DECIMALS: u8 = 4;
+
+/// A swap amount as requested: an exact decimal in ui units of its mint, or raw base units.
+/// Percentage sells take a fraction of the balance, or basis points when raw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Amount {
+    Ui(Decimal),
+    Raw(u64),
+}
+
+impl Amount {
+    pub fn new(amount: Decimal, raw: bool) -> Result<Self, AmountError> {
+        if amount.is_sign_negative() && !amount.is_zero() {
+            return Err(AmountError::Negative(amount));
+        }
+        if !raw {
+            return Ok(Amount::Ui(amount.normalize()));
+        }
+        if !amount.fract().is_zero() {
+            return Err(AmountError::Precision {
+                amount,
+                decimals: 0,
+            });
+        }
+        amount
+            .to_u64()
+            .map(Amount::Raw)
+            .ok_or(AmountError::Overflow(amount))
+    }
+
+    /// Raw units of a mint with `decimals`, more fractional digits than the mint has are
+    /// rejected instead of rounded.
+    pub fn to_raw(&self, decimals: u8) -> Result<u64, AmountError> {
+        let amount = match self {
+            Amount::Raw(amount) => return Ok(*amount),
+            Amount::Ui(amount) => *amount,
+        };
+        let scaled = 10u64
+            .checked_pow(decimals as u32)
+            .and_then(|scale| amount.checked_mul(Decimal::from(scale)))
+            .ok_or(AmountError::Overflow(amount))?;
+        if !scaled.fract().is_zero() {
+            return Err(AmountError::Precision { amount, decimals });
+        }
+        scaled.to_u64().ok_or(AmountError::Overflow(amount))
+    }
+
+    /// Share of a balance in basis points, at most [`MAX_BPS`].
+    pub fn to_bps(&self) -> Result<u64, AmountError> {
+        let bps = self.to_raw(BPS_DECIMALS)?;
+        if bps > MAX_BPS {
+            return Err(AmountError::PctOutOfRange(*self));
+        }
+        Ok(bps)
+    }
+
+    /// Part of `balance` a percentage amount takes, the whole balance at [`MAX_BPS`].
+    pub fn share_of(&self, balance: u64) -> Result<u64, AmountError> {
+        let bps = self.to_bps()?;
+        Ok((balance as u128 * bps as u128 / MAX_BPS as u128) as u64)
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.to_bps() == Ok(MAX_BPS)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Amount::Ui(amount) => write!(f, "{}", amount),
+            Amount::Raw(amount) => write!(f, "{} raw", amount),
+        }
+    }
+}
+
+/// Checks a sell does not spend more than the token account holds.
+pub fn check_balance(amount: u64, balance: u64) -> Result<(), AmountError> {
+    if amount > balance {
+        return Err(AmountError::ExceedsBalance { amount, balance });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    Negative(Decimal),
+    /// more fractional digits than the mint has
+    Precision {
+        amount: Decimal,
+        decimals: u8,
+    },
+    Overflow(Decimal),
+    PctOutOfRange(Amount),
+    ExceedsBalance {
+        amount: u64,
+        balance: u64,
+    },
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Negative(amount) => write!(f, "InvalidAmount: {} is negative", amount),
+            AmountError::Precision { amount, decimals } => write!(
+                f,
+                "InvalidAmount: {} has more than {} decimals",
+                amount, decimals
+            ),
+            AmountError::Overflow(amount) => write!(f, "InvalidAmount: {} is too large", amount),
+            AmountError::PctOutOfRange(amount) => write!(
+                f,
+                "InvalidAmount: percentage {} is above 1 ({} bps raw)",
+                amount, MAX_BPS
+            ),
+            AmountError::ExceedsBalance { amount, balance } => write!(
+                f,
+                "InvalidAmount: {} exceeds the balance {}",
+                amount, balance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn ui(amount: &str) -> Amount {
+        Amount::new(Decimal::from_str(amount).unwrap(), false).unwrap()
+    }
+
+    #[test]
+    fn test_amount_to_raw() {
+        // 0.1 + 0.2 style inputs stay exact
+        assert_eq!(ui("0.3").to_raw(9), Ok(300_000_000));
+        assert_eq!(ui("1.000000001").to_raw(9), Ok(1_000_000_001));
+        assert_eq!(ui("123456789.123456").to_raw(6), Ok(123_456_789_123_456));
+        assert!(matches!(
+            ui("1.0000000001").to_raw(9),
+            Err(AmountError::Precision { decimals: 9, .. })
+        ));
+        assert!(matches!(
+            ui("18446744073709551616").to_raw(0),
+            Err(AmountError::Overflow(_))
+        ));
+        assert_eq!(Amount::Raw(42).to_raw(9), Ok(42));
+
+        assert!(matches!(
+            Amount::new(Decimal::from_str("-1").unwrap(), false),
+            Err(AmountError::Negative(_))
+        ));
+        assert_eq!(
+            Amount::new(Decimal::from_str("1500").unwrap(), true),
+            Ok(Amount::Raw(1500))
+        );
+        assert!(Amount::new(Decimal::from_str("1.5").unwrap(), true).is_err());
+        assert!(Decimal::from_str("NaN").is_err());
+    }
+
+    #[test]
+    fn test_amount_share_of() {
+        assert_eq!(ui("0.1234").to_bps(), Ok(1234));
+        assert_eq!(ui("0.5").share_of(1_001), Ok(500));
+        assert_eq!(Amount::Raw(2_500).share_of(1_000), Ok(250));
+        assert!(ui("1").is_all());
+        assert!(Amount::Raw(MAX_BPS).is_all());
+        assert!(!ui("0.9999").is_all());
+        assert!(matches!(
+            ui("1.01").to_bps(),
+            Err(AmountError::PctOutOfRange(_))
+        ));
+        assert!(matches!(
+            ui("0.12345").to_bps(),
+            Err(AmountError::Precision { .. })
+        ));
+
+        assert!(check_balance(100, 100).is_ok());
+        assert_eq!(
+            check_balance(101, 100),
+            Err(AmountError::ExceedsBalance {
+                amount: 101,
+                balance: 100
+            })
+        );
+    }
+}
@@ -1,29 +1,36 @@
-use std::{env, str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc};
 
 use axum::{
     debug_handler,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::IntoResponse,
     Json,
 };
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use serde_json::json;
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
 use tracing::{info, warn};
 
 use crate::{
-    get_rpc_client,
-    helper::{api_error, api_ok},
+    amount::Amount,
+    helper::{api_error, api_error_with, api_ok},
+    priority_fee::PriorityFee,
     pump::{get_pump_info, RaydiumInfo},
-    raydium::Raydium,
-    swap::{self, SwapDirection, SwapInType},
+    quote,
+    raydium::{get_ranked_pools_by_mint, Raydium},
+    rpc_pool::RpcPool,
+    slippage::Slippage,
+    swap::{self, SwapDirection, SwapGuards, SwapInType, SwapOptions},
     token,
 };
 
 #[derive(Clone)]
 pub struct AppState {
+    /// sends through `rpc`
     pub client: Arc<RpcClient>,
+    pub rpc: Arc<RpcPool>,
     pub wallet: Arc<Keypair>,
 }
 
@@ -31,10 +38,20 @@ pub struct AppState {
 pub struct CreateSwap {
     mint: String,
     direction: SwapDirection,
-    amount_in: f64,
+    /// exact decimal, a number or a string
+    amount_in: Decimal,
+    /// amount_in is in raw base units, basis points for pct
+    raw: Option<bool>,
     in_type: Option<SwapInType>,
-    slippage: Option<u64>,
+    /// percent, or a string such as "50bps" or "0.5%"
+    slippage: Option<Slippage>,
     jito: Option<bool>,
+    /// fixed[:<micro-lamports>], percentile[:<0-100>] or max:<lamports>, PRIORITY_FEE by default
+    priority_fee: Option<PriorityFee>,
+    /// sol, usdc, usdt or a mint address, sol by default
+    quote_mint: Option<String>,
+    #[serde(flatten)]
+    guards: SwapGuards,
 }
 
 #[debug_handler]
@@ -42,29 +59,49 @@ pub async fn swap(
     State(state): State<AppState>,
     Json(input): Json<CreateSwap>,
 ) -> impl IntoResponse {
-    let slippage = match input.slippage {
-        Some(v) => v,
-        None => {
-            let slippage = env::var("SLIPPAGE").unwrap_or("5".to_string());
-            let slippage = slippage.parse::<u64>().unwrap_or(5);
-            slippage
-        }
+    let slippage = match input.slippage.map(Ok).unwrap_or_else(Slippage::from_env) {
+        Ok(slippage) => slippage,
+        Err(err) => return api_error(&err.to_string()),
     };
 
     info!("{:?}, slippage: {}", input, slippage);
+    let amount_in = match Amount::new(input.amount_in, input.raw.unwrap_or(false)) {
+        Ok(amount_in) => amount_in,
+        Err(err) => return api_error(&err.to_string()),
+    };
+    let quote_mint = match quote::parse_quote_mint(input.quote_mint.as_deref().unwrap_or("sol")) {
+        Ok(quote_mint) => quote_mint,
+        Err(err) => return api_error(&err.to_string()),
+    };
+    let priority_fee = match input
+        .priority_fee
+        .map(Ok)
+        .unwrap_or_else(PriorityFee::from_env)
+    {
+        Ok(priority_fee) => priority_fee,
+        Err(err) => return api_error(&err.to_string()),
+    };
 
     let result = swap::swap(
         state,
         input.mint.as_str(),
-        input.amount_in,
+        amount_in,
         input.direction.clone(),
         input.in_type.unwrap_or(SwapInType::Qty),
-        slippage,
-        input.jito.unwrap_or(false),
+        SwapOptions::new(slippage, input.jito.unwrap_or(false), priority_fee),
+        input.guards.clone().or(SwapGuards::from_env()),
+        quote_mint,
     )
     .await;
     match result {
-        Ok(txs) => api_ok(txs),
+        // a failed or expired send is not a success, the response still has its signatures
+        Ok(result) => match result.sent.clone().into_result() {
+            Ok(_) => api_ok(result),
+            Err(err) => {
+                warn!("swap not confirmed: {:#?}", err);
+                api_error_with(&err.to_string(), result)
+            }
+        },
         Err(err) => {
             warn!("swap err: {:#?}", err);
             api_error(&err.to_string())
@@ -72,17 +109,65 @@ pub async fn swap(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CreateQuote {
+    mint: String,
+    direction: SwapDirection,
+    /// exact decimal, a number or a string
+    amount_in: Decimal,
+    /// amount_in is in raw base units, basis points for pct
+    raw: Option<bool>,
+    in_type: Option<SwapInType>,
+    /// percent, or a string such as "50bps" or "0.5%"
+    slippage: Option<Slippage>,
+    quote_mint: Option<String>,
+}
+
 #[debug_handler]
-pub async fn get_pool(
+pub async fn quote(
     State(state): State<AppState>,
-    Path(pool_id): Path<String>,
+    Json(input): Json<CreateQuote>,
 ) -> impl IntoResponse {
-    let client = match get_rpc_client() {
-        Ok(client) => client,
+    let slippage = match input.slippage.map(Ok).unwrap_or_else(Slippage::from_env) {
+        Ok(slippage) => slippage,
+        Err(err) => return api_error(&err.to_string()),
+    };
+
+    info!("{:?}, slippage: {}", input, slippage);
+    let amount_in = match Amount::new(input.amount_in, input.raw.unwrap_or(false)) {
+        Ok(amount_in) => amount_in,
+        Err(err) => return api_error(&err.to_string()),
+    };
+    let quote_mint = match quote::parse_quote_mint(input.quote_mint.as_deref().unwrap_or("sol")) {
+        Ok(quote_mint) => quote_mint,
+        Err(err) => return api_error(&err.to_string()),
+    };
+
+    let result = quote::quote(
+        state,
+        input.mint.as_str(),
+        amount_in,
+        input.direction.clone(),
+        input.in_type.unwrap_or(SwapInType::Qty),
+        slippage,
+        quote_mint,
+    )
+    .await;
+    match result {
+        Ok(quote) => api_ok(quote),
         Err(err) => {
-            return api_error(&format!("failed to get rpc client: {err}"));
+            warn!("quote err: {:#?}", err);
+            api_error(&err.to_string())
         }
-    };
+    }
+}
+
+#[debug_handler]
+pub async fn get_pool(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+) -> impl IntoResponse {
+    let client = state.client.clone();
     let wallet = state.wallet;
     let swapx = Raydium::new(client, wallet);
     match swapx.get_pool(pool_id.as_str()).await {
@@ -100,13 +185,42 @@ pub async fn get_pool(
     }
 }
 
-pub async fn coins(State(state): State<AppState>, Path(mint): Path<String>) -> impl IntoResponse {
-    let client = match get_rpc_client() {
-        Ok(client) => client,
+#[derive(Debug, Deserialize)]
+pub struct PoolsQuery {
+    mint: String,
+}
+
+/// Raydium AMM v4 pools of the mint, ranked with the chosen one first.
+#[debug_handler]
+pub async fn get_pools(
+    State(state): State<AppState>,
+    Query(input): Query<PoolsQuery>,
+) -> impl IntoResponse {
+    let client = state.client.clone();
+    match get_ranked_pools_by_mint(client, &input.mint).await {
+        Ok(pools) => {
+            let pools = pools
+                .into_iter()
+                .map(|pool| pool.liquidity)
+                .collect::<Vec<_>>();
+            let pool_id = pools
+                .first()
+                .filter(|pool| pool.tradable)
+                .map(|pool| pool.pool_id.clone());
+            api_ok(json!({
+                "pool_id": pool_id,
+                "pools": pools,
+            }))
+        }
         Err(err) => {
-            return api_error(&format!("failed to get rpc client: {err}"));
+            warn!("get pools err: {:#?}", err);
+            api_error(&err.to_string())
         }
-    };
+    }
+}
+
+pub async fn coins(State(state): State<AppState>, Path(mint): Path<String>) -> impl IntoResponse {
+    let client = state.client.clone();
     let wallet = state.wallet;
     // query from pump.fun
     let mut pump_info = match get_pump_info(client.clone(), &mint).await {
@@ -131,17 +245,12 @@ pub async fn coins(State(state): State<AppState>, Path(mint): Path<String>) -> i
         }
     }
 
-    return api_ok(pump_info);
+    api_ok(pump_info)
 }
 
 #[debug_handler]
 pub async fn token_accounts(State(state): State<AppState>) -> impl IntoResponse {
-    let client = match get_rpc_client() {
-        Ok(client) => client,
-        Err(err) => {
-            return api_error(&format!("failed to get rpc client: {err}"));
-        }
-    };
+    let client = state.client.clone();
     let wallet = state.wallet;
 
     let token_accounts = token::token_accounts(&client, &wallet.pubkey()).await;
@@ -160,12 +269,7 @@ pub async fn token_account(
     State(state): State<AppState>,
     Path(mint): Path<String>,
 ) -> impl IntoResponse {
-    let client = match get_rpc_client() {
-        Ok(client) => client,
-        Err(err) => {
-            return api_error(&format!("failed to get rpc client: {err}"));
-        }
-    };
+    let client = state.client.clone();
     let wallet = state.wallet;
 
     let mint = if let Ok(mint) = Pubkey::from_str(mint.as_str()) {
@@ -184,3 +288,9 @@ pub async fn token_account(
         }
     }
 }
+
+/// Latency, slot lag and error rate of every rpc endpoint.
+#[debug_handler]
+pub async fn rpc_health(State(state): State<AppState>) -> impl IntoResponse {
+    api_ok(state.rpc.health())
+}
@@ -3,3 +3,9 @@ pub struct Symbol;
 impl Symbol {
     pub const SOLANA: &'static str = "solana";
 }
+
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+pub const USDC_DECIMALS: u8 = 6;
+pub const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY41NLw9zLVczP9";
+// a token without a SOL pool is routed SOL -> stable -> token through these
+pub const HOP_MINTS: [&str; 2] = [USDC_MINT, USDT_MINT];
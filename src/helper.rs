@@ -19,6 +19,14 @@ pub fn api_error(msg: &str) -> Json<Value> {
         "message": msg
     }))
 }
+/// An error that still carries what was done, such as a swap sent but not confirmed.
+pub fn api_error_with<T: Serialize>(msg: &str, data: T) -> Json<Value> {
+    Json(json!({
+        "status": "error",
+        "message": msg,
+        "data": data
+    }))
+}
 
 #[derive(Debug, Deserialize)]
 struct CurrencyData {
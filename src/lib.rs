@@ -3,18 +3,31 @@ use std::{env, sync::Arc};
 use anyhow::{anyhow, Result};
 use rand::seq::SliceRandom;
 use reqwest::Proxy;
-use solana_client::rpc_client::RpcClient;
+use rpc_pool::RpcPool;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::signature::Keypair;
 use tracing::debug;
 
+pub mod amount;
 pub mod api;
 pub mod constants;
 pub mod helper;
 pub mod jito;
 pub mod logger;
+pub mod lookup_table;
 pub mod pool;
+pub mod preflight;
+pub mod priority_fee;
 pub mod pump;
+pub mod pump_amm;
+pub mod quote;
 pub mod raydium;
+pub mod raydium_clmm;
+pub mod raydium_cpmm;
+pub mod router;
+pub mod rpc;
+pub mod rpc_pool;
+pub mod slippage;
 pub mod swap;
 pub mod token;
 pub mod tx;
@@ -35,36 +48,77 @@ pub fn get_client_build() -> Result<reqwest::Client> {
     }
 }
 
-pub fn get_random_rpc_url() -> Result<String> {
-    let cluster_urls = env::var("RPC_ENDPOINTS")?
-        .split(",")
+fn parse_rpc_urls(urls: &str) -> Vec<String> {
+    urls.split(",")
         .map(|s| s.trim().to_string())
-        .collect::<Vec<String>>();
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Host of an rpc url, urls are not reported whole as they may hold api keys.
+fn endpoint_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn get_random_rpc_url() -> Result<String> {
+    let cluster_urls = parse_rpc_urls(&env::var("RPC_ENDPOINTS")?);
     let random_url = cluster_urls
         .choose(&mut rand::thread_rng())
         .expect("No RPC endpoints configured")
         .clone();
 
     debug!("Choose rpc: {}", random_url);
-    return Ok(random_url);
+    Ok(random_url)
 }
 
+/// Endpoints a transaction is sent to at once when TX_FANOUT is true: every RPC_ENDPOINTS
+/// url and the send only SEND_RPC_ENDPOINTS, each once. Empty when fan out is off.
+pub fn get_fanout_rpc_urls() -> Result<Vec<String>> {
+    if env::var("TX_FANOUT").ok() != Some("true".to_string()) {
+        return Ok(vec![]);
+    }
+    let mut urls = parse_rpc_urls(&env::var("RPC_ENDPOINTS")?);
+    urls.extend(parse_rpc_urls(
+        &env::var("SEND_RPC_ENDPOINTS").unwrap_or_default(),
+    ));
+    let mut fanout_urls: Vec<String> = Vec::new();
+    for url in urls {
+        if !fanout_urls.contains(&url) {
+            fanout_urls.push(url);
+        }
+    }
+    Ok(fanout_urls)
+}
+
+/// Client over a pool of RPC_ENDPOINTS, without background health checks.
 pub fn get_rpc_client() -> Result<Arc<RpcClient>> {
-    let random_url = get_random_rpc_url()?;
-    let client = RpcClient::new(random_url);
-    return Ok(Arc::new(client));
+    Ok(RpcPool::from_env()?.client())
 }
 
 pub fn get_wallet() -> Result<Arc<Keypair>> {
     let wallet = Keypair::from_base58_string(&env::var("PRIVATE_KEY")?);
-    return Ok(Arc::new(wallet));
+    Ok(Arc::new(wallet))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[ctor::ctor]
     fn init() {
         crate::logger::init();
         dotenvy::dotenv().ok();
     }
+
+    #[test]
+    fn test_parse_rpc_urls() {
+        assert_eq!(
+            parse_rpc_urls("https://a.rpc, https://b.rpc/?api-key=1,,"),
+            vec!["https://a.rpc", "https://b.rpc/?api-key=1"]
+        );
+        assert!(parse_rpc_urls("").is_empty());
+    }
 }
@@ -0,0 +1,194 @@
+use std::{env, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use solana_address_lookup_table_program::{
+    instruction::{create_lookup_table, extend_lookup_table},
+    state::AddressLookupTable,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount, commitment_config::CommitmentConfig,
+    pubkey::Pubkey, signature::Keypair, signer::Signer, system_program, sysvar,
+};
+use tracing::info;
+
+use crate::{
+    constants::{USDC_MINT, USDT_MINT},
+    priority_fee::PriorityFee,
+    pump::{ASSOCIATED_TOKEN_PROGRAM, PUMP_ACCOUNT},
+    raydium::{self, AMM_AUTHORITY},
+    raydium_clmm::MEMO_PROGRAM,
+    tx,
+};
+
+/// Addresses added to a lookup table per transaction, more do not fit in one.
+const EXTEND_CHUNK_SIZE: usize = 20;
+
+/// LOOKUP_TABLES from the environment, comma separated lookup table addresses swaps are
+/// compiled against, none when unset.
+pub fn lookup_tables_from_env() -> Result<Vec<Pubkey>> {
+    let lookup_tables = env::var("LOOKUP_TABLES").unwrap_or_default();
+    parse_addresses(&lookup_tables)
+}
+
+/// Comma separated addresses, blanks are skipped.
+pub fn parse_addresses(addresses: &str) -> Result<Vec<Pubkey>> {
+    addresses
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            Pubkey::from_str(address).map_err(|e| anyhow!("invalid address {}: {}", address, e))
+        })
+        .collect()
+}
+
+/// Loads the lookup tables at `addresses`.
+pub async fn get_lookup_tables(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>> {
+    if addresses.is_empty() {
+        return Ok(vec![]);
+    }
+    let accounts = client.get_multiple_accounts(addresses).await?;
+    addresses
+        .iter()
+        .zip(accounts)
+        .map(|(key, account)| {
+            let account = account.ok_or(anyhow!(
+                "NotFoundLookupTable: lookup table {} not found",
+                key
+            ))?;
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|e| anyhow!("invalid lookup table {}: {}", key, e))?;
+            Ok(AddressLookupTableAccount {
+                key: *key,
+                addresses: table.addresses.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Programs, sysvars, mints and venue accounts most swaps touch.
+pub fn common_addresses() -> Result<Vec<Pubkey>> {
+    let mut addresses = vec![
+        system_program::ID,
+        sysvar::rent::ID,
+        spl_token::ID,
+        spl_token_2022::ID,
+        spl_token::native_mint::ID,
+    ];
+    for address in [
+        ASSOCIATED_TOKEN_PROGRAM,
+        USDC_MINT,
+        USDT_MINT,
+        AMM_AUTHORITY,
+        PUMP_ACCOUNT,
+        MEMO_PROGRAM,
+    ] {
+        addresses.push(Pubkey::from_str(address)?);
+    }
+    Ok(addresses)
+}
+
+/// Creates a lookup table owned and paid for by the wallet.
+pub async fn create(client: Arc<RpcClient>, keypair: Arc<Keypair>) -> Result<Pubkey> {
+    // the slot must be in the slot hashes sysvar, a finalized one always is
+    let recent_slot = client
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .await?;
+    let (instruction, lookup_table) =
+        create_lookup_table(keypair.pubkey(), keypair.pubkey(), recent_slot);
+    tx::new_signed_and_send(
+        &client,
+        &keypair,
+        vec![instruction],
+        tx::SendOptions::new(false, PriorityFee::from_env()?),
+    )
+    .await?
+    .into_result()?;
+    info!("lookup table created: {}", lookup_table);
+    Ok(lookup_table)
+}
+
+/// Adds the addresses the lookup table does not hold yet, returns how many were added.
+pub async fn extend(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    lookup_table: &Pubkey,
+    addresses: Vec<Pubkey>,
+) -> Result<usize> {
+    let table = get_lookup_tables(&client, &[*lookup_table])
+        .await?
+        .remove(0);
+    let mut new_addresses: Vec<Pubkey> = Vec::new();
+    for address in addresses {
+        if !table.addresses.contains(&address) && !new_addresses.contains(&address) {
+            new_addresses.push(address);
+        }
+    }
+    for chunk in new_addresses.chunks(EXTEND_CHUNK_SIZE) {
+        let instruction = extend_lookup_table(
+            *lookup_table,
+            keypair.pubkey(),
+            Some(keypair.pubkey()),
+            chunk.to_vec(),
+        );
+        tx::new_signed_and_send(
+            &client,
+            &keypair,
+            vec![instruction],
+            tx::SendOptions::new(false, PriorityFee::from_env()?),
+        )
+        .await?
+        .into_result()?;
+    }
+    info!(
+        "lookup table {} extended by {} addresses",
+        lookup_table,
+        new_addresses.len()
+    );
+    Ok(new_addresses.len())
+}
+
+/// Accounts of the deepest Raydium AMM v4 pool of the mint against SOL, including the
+/// accounts of its Serum/OpenBook market.
+pub async fn raydium_pool_addresses(client: Arc<RpcClient>, mint: &str) -> Result<Vec<Pubkey>> {
+    let (pool_id, pool_state) = raydium::get_pool_state_by_mint(client.clone(), mint).await?;
+    let market_accounts = raydium::get_market_accounts(&client, &pool_state).await?;
+    let mut addresses = vec![
+        pool_id,
+        pool_state.open_orders,
+        pool_state.target_orders,
+        pool_state.coin_vault,
+        pool_state.pc_vault,
+        pool_state.coin_vault_mint,
+        pool_state.pc_vault_mint,
+        pool_state.market,
+        pool_state.market_program,
+    ];
+    addresses.extend(market_accounts);
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addresses() {
+        assert!(parse_addresses("").unwrap().is_empty());
+        assert!(parse_addresses(" , ").unwrap().is_empty());
+        let addresses = parse_addresses(
+            "So11111111111111111111111111111111111111112, 11111111111111111111111111111111,",
+        )
+        .unwrap();
+        assert_eq!(
+            addresses,
+            vec![spl_token::native_mint::ID, system_program::ID]
+        );
+        assert!(parse_addresses("not an address").is_err());
+        assert_eq!(common_addresses().unwrap().len(), 11);
+    }
+}
@@ -6,12 +6,18 @@ use axum::{
 };
 use clap::{ArgGroup, Parser, Subcommand};
 use raytx::{
+    amount::Amount,
     api::{self, AppState},
-    get_rpc_client, get_wallet, jito, logger,
-    raydium::get_pool_info,
-    swap::{self, SwapDirection, SwapInType},
+    get_wallet, jito, logger, lookup_table,
+    priority_fee::PriorityFee,
+    quote,
+    raydium::{get_pool_info, get_ranked_pools_by_mint},
+    rpc_pool::RpcPool,
+    slippage::Slippage,
+    swap::{self, SwapDirection, SwapGuards, SwapInType, SwapOptions},
     token,
 };
+use rust_decimal::Decimal;
 use std::{env, net::SocketAddr, str::FromStr};
 use tower_http::cors::CorsLayer;
 use tracing::{debug, info};
@@ -31,18 +37,104 @@ enum Command {
     #[command(group(
         ArgGroup::new("amount")
             .required(true)
-            .args(&["amount_in", "amount_in_pct"]),
+            .args(&["amount_in", "amount_in_pct", "amount_out", "amount_in_usd"]),
     ))]
     Swap {
         mint: String,
         #[arg(value_enum)]
         direction: SwapDirection,
         #[arg(long, help = "amount in")]
-        amount_in: Option<f64>,
-        #[arg(long, help = "amount in percentage, only support sell")]
-        amount_in_pct: Option<f64>,
+        amount_in: Option<Decimal>,
+        #[arg(
+            long,
+            help = "amount in percentage, of the token balance for sell and of the spendable SOL for buy"
+        )]
+        amount_in_pct: Option<Decimal>,
+        #[arg(long, help = "amount in USD, only support buy")]
+        amount_in_usd: Option<Decimal>,
+        #[arg(
+            long,
+            help = "exact amount out, the amount in is capped by slippage, only support raydium amm and pump fun buy"
+        )]
+        amount_out: Option<Decimal>,
+        #[arg(
+            long,
+            help = "amounts are in raw base units, basis points for amount in percentage",
+            default_value_t = false
+        )]
+        raw: bool,
+        #[arg(
+            long,
+            help = "slippage in percent or bps, such as 0.5 or 50bps, SLIPPAGE by default"
+        )]
+        slippage: Option<Slippage>,
         #[arg(long, help = "use jito to swap", default_value_t = false)]
         jito: bool,
+        #[arg(
+            long,
+            help = "priority fee: fixed[:<micro-lamports>], percentile[:<0-100>] or max:<lamports>, PRIORITY_FEE by default"
+        )]
+        priority_fee: Option<PriorityFee>,
+        #[arg(long, help = "reject the swap above this price impact percentage")]
+        max_price_impact: Option<f64>,
+        #[arg(
+            long,
+            help = "reject the swap if the pool holds less of the quote mint (SOL by default) than this"
+        )]
+        min_liquidity: Option<f64>,
+        #[arg(
+            long,
+            help = "reject the swap if it takes more than this percentage of the pool reserve"
+        )]
+        max_reserve_pct: Option<f64>,
+        #[arg(
+            long,
+            help = "mint to buy with and sell into: sol, usdc, usdt or a mint address, only support raydium amm",
+            default_value = "sol"
+        )]
+        quote_mint: String,
+    },
+    #[command(about = "quote a swap of the mint token without sending it")]
+    #[command(group(
+        ArgGroup::new("amount")
+            .required(true)
+            .args(&["amount_in", "amount_in_pct", "amount_out", "amount_in_usd"]),
+    ))]
+    Quote {
+        mint: String,
+        #[arg(value_enum)]
+        direction: SwapDirection,
+        #[arg(long, help = "amount in")]
+        amount_in: Option<Decimal>,
+        #[arg(
+            long,
+            help = "amount in percentage, of the token balance for sell and of the spendable SOL for buy"
+        )]
+        amount_in_pct: Option<Decimal>,
+        #[arg(long, help = "amount in USD, only support buy")]
+        amount_in_usd: Option<Decimal>,
+        #[arg(
+            long,
+            help = "exact amount out, only support raydium amm and pump fun buy"
+        )]
+        amount_out: Option<Decimal>,
+        #[arg(
+            long,
+            help = "amounts are in raw base units, basis points for amount in percentage",
+            default_value_t = false
+        )]
+        raw: bool,
+        #[arg(
+            long,
+            help = "slippage in percent or bps, such as 0.5 or 50bps, SLIPPAGE by default"
+        )]
+        slippage: Option<Slippage>,
+        #[arg(
+            long,
+            help = "mint to buy with and sell into: sol, usdc, usdt or a mint address, only support raydium amm",
+            default_value = "sol"
+        )]
+        quote_mint: String,
     },
     Daemon {
         #[arg(
@@ -58,6 +150,11 @@ enum Command {
     Unwrap {},
     #[command(subcommand)]
     Token(TokenCommand),
+    #[command(
+        subcommand,
+        about = "Manage address lookup tables swaps are compiled against"
+    )]
+    Alt(AltCommand),
 }
 
 #[derive(Subcommand, Debug)]
@@ -70,6 +167,31 @@ enum TokenCommand {
         mint: String,
     },
 }
+#[derive(Subcommand, Debug)]
+enum AltCommand {
+    #[command(about = "Create an address lookup table owned by the wallet")]
+    Create,
+    #[command(about = "Add addresses to an address lookup table")]
+    Extend {
+        #[arg(help = "The address of the lookup table")]
+        lookup_table: String,
+        #[arg(help = "Addresses to add")]
+        addresses: Vec<String>,
+        #[arg(
+            long,
+            help = "add the programs, sysvars, mints and venue accounts most swaps use",
+            default_value_t = false
+        )]
+        common: bool,
+        #[arg(long, help = "add the accounts of the raydium amm pool of this mint")]
+        pool_mint: Option<String>,
+    },
+    #[command(about = "Show the addresses of an address lookup table")]
+    Show {
+        #[arg(help = "The address of the lookup table")]
+        lookup_table: String,
+    },
+}
 #[tokio::main]
 async fn main() -> Result<()> {
     if let Ok(env_path) = env::var("DOTENV_PATH") {
@@ -80,9 +202,14 @@ async fn main() -> Result<()> {
     }
     let cli = Cli::parse();
     logger::init();
-    let client = get_rpc_client()?;
+    let rpc = RpcPool::from_env()?;
+    let client = rpc.client();
     let wallet = get_wallet()?;
-    let app_state = AppState { client, wallet };
+    let app_state = AppState {
+        client,
+        rpc,
+        wallet,
+    };
 
     match &cli.command {
         Some(Command::Swap {
@@ -90,17 +217,33 @@ async fn main() -> Result<()> {
             direction,
             amount_in,
             amount_in_pct,
+            amount_in_usd,
+            amount_out,
+            raw,
+            slippage,
             jito,
+            priority_fee,
+            max_price_impact,
+            min_liquidity,
+            max_reserve_pct,
+            quote_mint,
         }) => {
             let (amount_in, in_type) = if let Some(amount_in) = amount_in {
                 (amount_in, SwapInType::Qty)
             } else if let Some(amount_in) = amount_in_pct {
                 (amount_in, SwapInType::Pct)
+            } else if let Some(amount_out) = amount_out {
+                (amount_out, SwapInType::ExactOut)
+            } else if let Some(amount_in) = amount_in_usd {
+                (amount_in, SwapInType::Usd)
             } else {
-                panic!("either in_amount or in_amount_pct must be provided");
+                panic!(
+                    "either in_amount, in_amount_pct, amount_in_usd or amount_out must be provided"
+                );
             };
-            let slippage = env::var("SLIPPAGE").unwrap_or("5".to_string());
-            let slippage = slippage.parse::<u64>().unwrap_or(5);
+            let amount_in = Amount::new(*amount_in, *raw)?;
+            let slippage = slippage.map(Ok).unwrap_or_else(Slippage::from_env)?;
+            let priority_fee = priority_fee.map(Ok).unwrap_or_else(PriorityFee::from_env)?;
             debug!(
                 "{} {:?} {:?} {:?} slippage: {}",
                 mint, direction, amount_in, in_type, slippage
@@ -123,16 +266,63 @@ async fn main() -> Result<()> {
                     .unwrap();
             }
 
-            swap::swap(
+            let result = swap::swap(
+                app_state,
+                mint,
+                amount_in,
+                direction.clone(),
+                in_type,
+                SwapOptions::new(slippage, *jito, priority_fee),
+                SwapGuards {
+                    max_price_impact_pct: *max_price_impact,
+                    min_quote_liquidity: *min_liquidity,
+                    max_reserve_pct: *max_reserve_pct,
+                }
+                .or(SwapGuards::from_env()),
+                quote::parse_quote_mint(quote_mint)?,
+            )
+            .await?;
+            info!("swap: {:#?}", result);
+            result.sent.into_result()?;
+        }
+        Some(Command::Quote {
+            mint,
+            direction,
+            amount_in,
+            amount_in_pct,
+            amount_in_usd,
+            amount_out,
+            raw,
+            slippage,
+            quote_mint,
+        }) => {
+            let (amount_in, in_type) = if let Some(amount_in) = amount_in {
+                (amount_in, SwapInType::Qty)
+            } else if let Some(amount_in) = amount_in_pct {
+                (amount_in, SwapInType::Pct)
+            } else if let Some(amount_out) = amount_out {
+                (amount_out, SwapInType::ExactOut)
+            } else if let Some(amount_in) = amount_in_usd {
+                (amount_in, SwapInType::Usd)
+            } else {
+                panic!(
+                    "either in_amount, in_amount_pct, amount_in_usd or amount_out must be provided"
+                );
+            };
+            let amount_in = Amount::new(*amount_in, *raw)?;
+            let slippage = slippage.map(Ok).unwrap_or_else(Slippage::from_env)?;
+
+            let quote = quote::quote(
                 app_state,
                 mint,
-                *amount_in,
+                amount_in,
                 direction.clone(),
                 in_type,
                 slippage,
-                *jito,
+                quote::parse_quote_mint(quote_mint)?,
             )
             .await?;
+            info!("quote: {:#?}", quote);
         }
         Some(Command::Daemon { addr }) => {
             jito::init_tip_accounts().await.unwrap();
@@ -142,15 +332,20 @@ async fn main() -> Result<()> {
                     .expect("Failed to get tip percentiles data");
             });
 
+            app_state.rpc.spawn_health_checks();
+
             let app = Router::new()
                 .nest(
                     "/api",
                     Router::new()
                         .route("/swap", post(api::swap))
+                        .route("/quote", post(api::quote))
+                        .route("/pool", get(api::get_pools))
                         .route("/pool/:pool_id", get(api::get_pool))
                         .route("/coins/:mint", get(api::coins))
                         .route("/token_accounts", get(api::token_accounts))
                         .route("/token_accounts/:mint", get(api::token_account))
+                        .route("/rpc/health", get(api::rpc_health))
                         .with_state(app_state),
                 )
                 .layer(
@@ -186,13 +381,61 @@ async fn main() -> Result<()> {
                     token::token_account(&app_state.client, &app_state.wallet.pubkey(), mint)
                         .await?;
                 info!("token_account: {:#?}", token_account);
-                let pool_info = get_pool_info(
-                    &spl_token::native_mint::id().to_string(),
-                    &token_account.mint,
-                )
-                .await?;
-                let pool_id = pool_info.get_pool().unwrap().id;
-                info!("pool id: {}", pool_id);
+                match get_ranked_pools_by_mint(app_state.client.clone(), &token_account.mint).await
+                {
+                    Ok(pools) => {
+                        if let Some(pool) = pools.first().filter(|pool| pool.liquidity.tradable) {
+                            info!("pool id: {}", pool.pool_id);
+                        }
+                        let pools = pools
+                            .into_iter()
+                            .map(|pool| pool.liquidity)
+                            .collect::<Vec<_>>();
+                        info!("pools: {:#?}", pools);
+                    }
+                    Err(err) => {
+                        debug!("no amm pool found by rpc: {}", err);
+                        let pool_info = get_pool_info(
+                            &spl_token::native_mint::id().to_string(),
+                            &token_account.mint,
+                        )
+                        .await?;
+                        let pool_id = pool_info.get_pool().unwrap().id;
+                        info!("pool id: {}", pool_id);
+                    }
+                }
+            }
+        },
+        Some(Command::Alt(alt_command)) => match alt_command {
+            AltCommand::Create => {
+                let lookup_table = lookup_table::create(app_state.client, app_state.wallet).await?;
+                info!("add it to LOOKUP_TABLES to use it: {}", lookup_table);
+            }
+            AltCommand::Extend {
+                lookup_table,
+                addresses,
+                common,
+                pool_mint,
+            } => {
+                let lookup_table = Pubkey::from_str(lookup_table)?;
+                let mut addresses = lookup_table::parse_addresses(&addresses.join(","))?;
+                if *common {
+                    addresses.extend(lookup_table::common_addresses()?);
+                }
+                if let Some(pool_mint) = pool_mint {
+                    addresses.extend(
+                        lookup_table::raydium_pool_addresses(app_state.client.clone(), pool_mint)
+                            .await?,
+                    );
+                }
+                lookup_table::extend(app_state.client, app_state.wallet, &lookup_table, addresses)
+                    .await?;
+            }
+            AltCommand::Show { lookup_table } => {
+                let lookup_table = Pubkey::from_str(lookup_table)?;
+                let lookup_tables =
+                    lookup_table::get_lookup_tables(&app_state.client, &[lookup_table]).await?;
+                info!("addresses: {:#?}", lookup_tables[0].addresses);
             }
         },
         _ => {}
@@ -31,7 +31,11 @@ impl Raydium {
         // debug!("pool_state : {:#?}", pool_state);
 
         let load_pubkeys = vec![pool_state.pc_vault, pool_state.coin_vault];
-        let rsps = common::rpc::get_multiple_accounts(&self.client, &load_pubkeys).unwrap();
+        let rsps = self
+            .client
+            .get_multiple_accounts(&load_pubkeys)
+            .await
+            .unwrap();
 
         let amm_pc_vault_account = rsps[0].clone();
         let amm_coin_vault_account = rsps[1].clone();
@@ -0,0 +1,282 @@
+use std::{env, fmt, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token::{amount_to_ui_amount, state::Account};
+use tracing::info;
+
+use crate::{
+    amount::Amount,
+    quote::Quote,
+    slippage::Slippage,
+    swap::Venue,
+    token,
+    tx::{self, SendOptions},
+};
+
+/// Used when MIN_SOL_RESERVE is not set, in lamports.
+const DEFAULT_MIN_SOL_RESERVE: u64 = 10_000_000;
+
+/// MIN_SOL_RESERVE from the environment, the SOL a swap never spends below and percentage
+/// buys keep back, 0.01 SOL when unset.
+pub fn min_sol_reserve() -> Result<u64> {
+    let reserve = match env::var("MIN_SOL_RESERVE") {
+        Ok(reserve) => reserve,
+        Err(_) => return Ok(DEFAULT_MIN_SOL_RESERVE),
+    };
+    let reserve = Decimal::from_str(reserve.trim())
+        .map_err(|_| anyhow!("InvalidAmount: MIN_SOL_RESERVE {} is not a number", reserve))?;
+    Ok(Amount::new(reserve, false)?.to_raw(spl_token::native_mint::DECIMALS)?)
+}
+
+/// Lamports a swap needs in the wallet before it is sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SwapCost {
+    /// most SOL the swap can take, see [`max_sol_in`]
+    pub amount: u64,
+    /// rent of the temporary wsol account, returned when it is closed
+    pub wsol_rent: u64,
+    /// rent of token accounts the swap creates
+    pub ata_rent: u64,
+    /// base and priority fees
+    pub fee: u64,
+    pub jito_tip: u64,
+    /// left in the wallet after the swap
+    pub reserve: u64,
+}
+
+impl SwapCost {
+    pub fn total(&self) -> u64 {
+        self.amount
+            .saturating_add(self.wsol_rent)
+            .saturating_add(self.ata_rent)
+            .saturating_add(self.fee)
+            .saturating_add(self.jito_tip)
+            .saturating_add(self.reserve)
+    }
+
+    pub fn check(&self, balance: u64) -> Result<(), BalanceError> {
+        if balance < self.total() {
+            return Err(BalanceError {
+                balance,
+                cost: self.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The wallet cannot pay for a swap and keep the reserve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceError {
+    pub balance: u64,
+    pub cost: SwapCost,
+}
+
+impl fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sol = |lamports: u64| amount_to_ui_amount(lamports, spl_token::native_mint::DECIMALS);
+        let cost = &self.cost;
+        write!(
+            f,
+            "InsufficientBalance: short {} SOL, need {} SOL (amount {}, wsol rent {}, ata rent {}, fee {}, jito tip {}, reserve {}), have {} SOL",
+            sol(cost.total() - self.balance),
+            sol(cost.total()),
+            sol(cost.amount),
+            sol(cost.wsol_rent),
+            sol(cost.ata_rent),
+            sol(cost.fee),
+            sol(cost.jito_tip),
+            sol(cost.reserve),
+            sol(self.balance),
+        )
+    }
+}
+
+impl std::error::Error for BalanceError {}
+
+/// Rent of the wallet's associated account of `mint`, nothing when it already exists.
+async fn ata_rent(client: Arc<RpcClient>, owner: &Pubkey, mint: &str) -> Result<u64> {
+    let mint = Pubkey::from_str(mint)?;
+    let (mint_info, program_id) = token::get_mint_info_with_program(client.clone(), &mint).await?;
+    let ata = get_associated_token_address_with_program_id(owner, &mint, &program_id);
+    if client.get_account(&ata).await.is_ok() {
+        return Ok(0);
+    }
+    let len = token::ata_len(&mint_info, &program_id)?;
+    Ok(client.get_minimum_balance_for_rent_exemption(len).await?)
+}
+
+/// Most SOL the swap quoted by `quote` can take from the wallet, nothing for sells. The slippage
+/// bound of exact out buys, and of exact in buys on the pump venues as well: the bonding curve
+/// buy allows a max SOL cost padded by `slippage` and PumpSwap funds its wsol account with it.
+pub fn max_sol_in(quote: &Quote, slippage: Slippage) -> u64 {
+    if quote.input_mint != spl_token::native_mint::ID.to_string() {
+        return 0;
+    }
+    match quote.venue {
+        Venue::Pump | Venue::PumpAmm => quote
+            .max_amount_in
+            .max(slippage.max_amount(quote.amount_in)),
+        _ => quote.max_amount_in,
+    }
+}
+
+/// What the swap quoted by `quote` at `slippage` costs the wallet in SOL.
+pub async fn swap_cost(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    quote: &Quote,
+    slippage: Slippage,
+    send: &SendOptions,
+) -> Result<SwapCost> {
+    let native_mint = spl_token::native_mint::ID.to_string();
+    let owner = keypair.pubkey();
+    let amount = max_sol_in(quote, slippage);
+    // the bonding curve takes and pays SOL directly, the pools go through wsol
+    let wsol_rent = if quote.venue != Venue::Pump
+        && (quote.input_mint == native_mint || quote.output_mint == native_mint)
+    {
+        client
+            .get_minimum_balance_for_rent_exemption(Account::LEN)
+            .await?
+    } else {
+        0
+    };
+    let mut ata_rent_total = 0;
+    for mint in [Some(&quote.output_mint), quote.via_mint.as_ref()]
+        .into_iter()
+        .flatten()
+        .filter(|mint| **mint != native_mint)
+    {
+        ata_rent_total += ata_rent(client.clone(), &owner, mint).await?;
+    }
+    Ok(SwapCost {
+        amount,
+        wsol_rent,
+        ata_rent: ata_rent_total,
+        fee: tx::max_fee_lamports(send.use_jito, send.priority_fee),
+        jito_tip: send.jito_tip().await?,
+        reserve: min_sol_reserve()?,
+    })
+}
+
+/// What a buy of `mint` costs the wallet on top of the SOL it swaps: account rent, fees and
+/// the jito tip. Counts a wsol account for every venue, the bonding curve does not need one.
+pub async fn buy_overhead(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    mint: &str,
+    send: &SendOptions,
+) -> Result<u64> {
+    let wsol_rent = client
+        .get_minimum_balance_for_rent_exemption(Account::LEN)
+        .await?;
+    let ata_rent = ata_rent(client.clone(), &keypair.pubkey(), mint).await?;
+    let fee = tx::max_fee_lamports(send.use_jito, send.priority_fee);
+    Ok(wsol_rent + ata_rent + fee + send.jito_tip().await?)
+}
+
+/// Checks the wallet holds enough SOL for the swap quoted by `quote` at `slippage` and the
+/// reserve, so a swap that cannot land is not sent.
+pub async fn check_balance(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    quote: &Quote,
+    slippage: Slippage,
+    send: &SendOptions,
+) -> Result<SwapCost> {
+    let cost = swap_cost(client.clone(), keypair.clone(), quote, slippage, send).await?;
+    let balance = client.get_balance(&keypair.pubkey()).await?;
+    info!("balance: {}, swap cost: {:?}", balance, cost);
+    cost.check(balance)?;
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap::SwapDirection;
+
+    #[test]
+    fn test_swap_cost_check() {
+        let cost = SwapCost {
+            amount: 1_000_000_000,
+            wsol_rent: 2_039_280,
+            ata_rent: 2_039_280,
+            fee: 4_005_000,
+            jito_tip: 0,
+            reserve: 10_000_000,
+        };
+        assert_eq!(cost.total(), 1_018_083_560);
+        assert!(cost.check(1_018_083_560).is_ok());
+
+        let err = cost.check(1_000_000_000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InsufficientBalance: short 0.01808356 SOL, need 1.01808356 SOL (amount 1, wsol rent 0.00203928, ata rent 0.00203928, fee 0.004005, jito tip 0, reserve 0.01), have 1 SOL"
+        );
+    }
+
+    #[test]
+    fn test_max_sol_in() {
+        let slippage = Slippage::from_bps(500).unwrap();
+        let quote = |venue: Venue, swap_direction: &SwapDirection, exact_out: bool| {
+            let mut quote = Quote::new(
+                venue,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                6,
+                swap_direction,
+            );
+            quote.amount_in = 1_000_000_000;
+            quote.amount_out = 2_000_000_000;
+            quote.with_slippage(slippage, exact_out)
+        };
+
+        // exact in buys on the pump venues may take the padded amount
+        let buy = &SwapDirection::Buy;
+        assert_eq!(
+            max_sol_in(&quote(Venue::Pump, buy, false), slippage),
+            1_050_000_000
+        );
+        assert_eq!(
+            max_sol_in(&quote(Venue::PumpAmm, buy, false), slippage),
+            1_050_000_000
+        );
+        // the raydium pools spend at most the exact input
+        assert_eq!(
+            max_sol_in(&quote(Venue::RaydiumAmm, buy, false), slippage),
+            1_000_000_000
+        );
+        assert_eq!(
+            max_sol_in(&quote(Venue::RaydiumAmm, buy, true), slippage),
+            1_050_000_000
+        );
+        assert_eq!(
+            max_sol_in(&quote(Venue::PumpAmm, buy, true), slippage),
+            1_050_000_000
+        );
+        assert_eq!(
+            max_sol_in(
+                &quote(Venue::PumpAmm, &SwapDirection::Sell, false),
+                slippage
+            ),
+            0
+        );
+
+        let cost = SwapCost {
+            amount: max_sol_in(&quote(Venue::PumpAmm, buy, false), slippage),
+            wsol_rent: 2_039_280,
+            fee: 4_005_000,
+            reserve: 10_000_000,
+            ..SwapCost::default()
+        };
+        assert!(cost.check(1_016_044_280).is_err());
+        assert!(cost.check(1_066_044_280).is_ok());
+    }
+}
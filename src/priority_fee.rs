@@ -0,0 +1,271 @@
+use std::{env, fmt, str::FromStr};
+
+use anyhow::Result;
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use tracing::{debug, info};
+
+use crate::tx::get_unit_price;
+
+/// Micro-lamports in a lamport, unit prices are in micro-lamports per compute unit.
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+/// Used when PRIORITY_FEE_PERCENTILE is not set.
+const DEFAULT_PERCENTILE: u8 = 75;
+/// Used when PRIORITY_FEE_MAX_LAMPORTS is not set, 0.001 SOL.
+const DEFAULT_MAX_LAMPORTS: u64 = 1_000_000;
+/// Most accounts getRecentPrioritizationFees takes.
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+/// How the compute unit price of a swap is chosen.
+///
+/// Parsed from `fixed` (UNIT_PRICE), `fixed:<micro-lamports>`, `percentile` (PRIORITY_FEE_PERCENTILE),
+/// `percentile:<0-100>` or `max:<lamports>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum PriorityFee {
+    /// unit price in micro-lamports
+    Fixed(u64),
+    /// percentile of the fees recently paid to write the accounts of the swap, capped at
+    /// PRIORITY_FEE_MAX_LAMPORTS
+    Percentile(u8),
+    /// spend this many lamports on the priority fee at the unit limit
+    MaxLamports(u64),
+}
+
+impl PriorityFee {
+    /// PRIORITY_FEE from the environment, the fixed UNIT_PRICE when unset.
+    pub fn from_env() -> Result<Self, PriorityFeeError> {
+        match env::var("PRIORITY_FEE") {
+            Ok(priority_fee) => priority_fee.parse(),
+            Err(_) => Ok(PriorityFee::Fixed(get_unit_price())),
+        }
+    }
+
+    /// Unit price in micro-lamports for `instructions` run with `unit_limit` compute units.
+    pub async fn unit_price(
+        &self,
+        client: &RpcClient,
+        instructions: &[Instruction],
+        unit_limit: u32,
+    ) -> Result<u64> {
+        let unit_price = match self {
+            PriorityFee::Fixed(unit_price) => *unit_price,
+            PriorityFee::Percentile(percentile) => {
+                let accounts = writable_accounts(instructions);
+                let fees = client
+                    .get_recent_prioritization_fees(&accounts)
+                    .await?
+                    .into_iter()
+                    .map(|fee| fee.prioritization_fee)
+                    .collect::<Vec<_>>();
+                let estimate = percentile_fee(fees, *percentile);
+                let cap = budget_unit_price(max_lamports_from_env(), unit_limit);
+                debug!(
+                    "p{} priority fee of {} accounts: {}, cap: {}",
+                    percentile,
+                    accounts.len(),
+                    estimate,
+                    cap
+                );
+                estimate.min(cap)
+            }
+            PriorityFee::MaxLamports(lamports) => budget_unit_price(*lamports, unit_limit),
+        };
+        info!("priority fee: {}, unit price: {}", self, unit_price);
+        Ok(unit_price)
+    }
+
+    /// Most lamports the priority fee takes at `unit_limit`, without asking the rpc.
+    pub fn max_lamports(&self, unit_limit: u32) -> u64 {
+        match self {
+            PriorityFee::Fixed(unit_price) => (*unit_price as u128 * unit_limit as u128)
+                .div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+                as u64,
+            PriorityFee::Percentile(_) => max_lamports_from_env(),
+            PriorityFee::MaxLamports(lamports) => *lamports,
+        }
+    }
+}
+
+impl Default for PriorityFee {
+    fn default() -> Self {
+        PriorityFee::Fixed(get_unit_price())
+    }
+}
+
+impl FromStr for PriorityFee {
+    type Err = PriorityFeeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || PriorityFeeError(s.to_string());
+        let (strategy, value) = match s.split_once(':') {
+            Some((strategy, value)) => (strategy.trim(), Some(value.trim())),
+            None => (s, None),
+        };
+        match (strategy.to_lowercase().as_str(), value) {
+            ("fixed", None) => Ok(PriorityFee::Fixed(get_unit_price())),
+            ("fixed", Some(unit_price)) => unit_price
+                .parse()
+                .map(PriorityFee::Fixed)
+                .map_err(|_| invalid()),
+            ("percentile", None) => Ok(PriorityFee::Percentile(percentile_from_env())),
+            ("percentile", Some(percentile)) => match percentile.parse::<u8>() {
+                Ok(percentile) if percentile <= 100 => Ok(PriorityFee::Percentile(percentile)),
+                _ => Err(invalid()),
+            },
+            ("max", Some(lamports)) => lamports
+                .parse()
+                .map(PriorityFee::MaxLamports)
+                .map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for PriorityFee {
+    type Error = PriorityFeeError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for PriorityFee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriorityFee::Fixed(unit_price) => write!(f, "fixed:{}", unit_price),
+            PriorityFee::Percentile(percentile) => write!(f, "percentile:{}", percentile),
+            PriorityFee::MaxLamports(lamports) => write!(f, "max:{}", lamports),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriorityFeeError(String);
+
+impl fmt::Display for PriorityFeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "InvalidPriorityFee: {}, expected fixed[:<micro-lamports>], percentile[:<0-100>] or max:<lamports>",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for PriorityFeeError {}
+
+/// PRIORITY_FEE_PERCENTILE from the environment, 75 when unset or invalid.
+fn percentile_from_env() -> u8 {
+    env::var("PRIORITY_FEE_PERCENTILE")
+        .ok()
+        .and_then(|v| v.trim().parse::<u8>().ok())
+        .filter(|percentile| *percentile <= 100)
+        .unwrap_or(DEFAULT_PERCENTILE)
+}
+
+/// PRIORITY_FEE_MAX_LAMPORTS from the environment, the most a percentile fee may cost.
+fn max_lamports_from_env() -> u64 {
+    env::var("PRIORITY_FEE_MAX_LAMPORTS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_LAMPORTS)
+}
+
+/// Unit price spending `lamports` at `unit_limit` compute units, rounded down.
+pub fn budget_unit_price(lamports: u64, unit_limit: u32) -> u64 {
+    if unit_limit == 0 {
+        return 0;
+    }
+    (lamports as u128 * MICRO_LAMPORTS_PER_LAMPORT / unit_limit as u128).min(u64::MAX as u128)
+        as u64
+}
+
+/// Nearest rank `percentile` of `fees`, 0 without fees.
+pub fn percentile_fee(mut fees: Vec<u64>, percentile: u8) -> u64 {
+    if fees.is_empty() {
+        return 0;
+    }
+    fees.sort_unstable();
+    let rank = (fees.len() * percentile.min(100) as usize).div_ceil(100);
+    fees[rank.saturating_sub(1)]
+}
+
+/// Accounts the instructions write, other than signers, whose recent fees price the swap.
+pub fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !meta.is_signer && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts.truncate(MAX_FEE_ACCOUNTS);
+    accounts
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::instruction::AccountMeta;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_priority_fee() {
+        assert_eq!(
+            "fixed:50000".parse::<PriorityFee>(),
+            Ok(PriorityFee::Fixed(50_000))
+        );
+        assert_eq!(
+            "percentile:90".parse::<PriorityFee>(),
+            Ok(PriorityFee::Percentile(90))
+        );
+        assert_eq!(
+            "max:100000".parse::<PriorityFee>(),
+            Ok(PriorityFee::MaxLamports(100_000))
+        );
+        assert!("percentile:101".parse::<PriorityFee>().is_err());
+        assert!("max".parse::<PriorityFee>().is_err());
+        assert!("fast".parse::<PriorityFee>().is_err());
+        assert_eq!(PriorityFee::MaxLamports(5).to_string(), "max:5");
+
+        let priority_fee: PriorityFee = serde_json::from_str("\"percentile:50\"").unwrap();
+        assert_eq!(priority_fee, PriorityFee::Percentile(50));
+    }
+
+    #[test]
+    fn test_priority_fee_estimate() {
+        assert_eq!(percentile_fee(vec![], 75), 0);
+        let fees = vec![0, 0, 100, 400, 200, 300, 0, 500, 1_000, 0];
+        assert_eq!(percentile_fee(fees.clone(), 50), 100);
+        assert_eq!(percentile_fee(fees.clone(), 75), 400);
+        assert_eq!(percentile_fee(fees.clone(), 100), 1_000);
+        assert_eq!(percentile_fee(fees, 0), 0);
+
+        // 0.001 SOL over 200k compute units
+        assert_eq!(budget_unit_price(1_000_000, 200_000), 5_000_000);
+        assert_eq!(PriorityFee::Fixed(20_000).max_lamports(200_000), 4_000);
+        assert_eq!(PriorityFee::MaxLamports(1_000).max_lamports(200_000), 1_000);
+
+        let signer = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let instructions = vec![
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![
+                    AccountMeta::new(signer, true),
+                    AccountMeta::new(pool, false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new(pool, false)],
+            ),
+        ];
+        assert_eq!(writable_accounts(&instructions), vec![pool]);
+    }
+}
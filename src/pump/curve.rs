@@ -0,0 +1,377 @@
+use anyhow::{anyhow, Result};
+use raydium_amm::math::U128;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use super::{BondingCurveAccount, BondingCurveError};
+use crate::amount::MAX_BPS;
+
+pub const INITIAL_VIRTUAL_TOKEN_RESERVES: u64 = 1_073_000_000_000_000;
+pub const INITIAL_VIRTUAL_SOL_RESERVES: u64 = 30_000_000_000;
+pub const INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+
+/// Fees charged by the bonding curve, in bps of the SOL side of the trade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CurveFees {
+    pub protocol_fee_bps: u64,
+    pub creator_fee_bps: u64,
+}
+
+impl CurveFees {
+    pub fn new(protocol_fee_bps: u64, creator_fee_bps: u64, creator: &Pubkey) -> Self {
+        Self {
+            protocol_fee_bps,
+            // the creator fee is only charged when the curve has a creator
+            creator_fee_bps: if *creator == Pubkey::default() {
+                0
+            } else {
+                creator_fee_bps
+            },
+        }
+    }
+
+    pub fn total_bps(&self) -> u64 {
+        self.protocol_fee_bps.saturating_add(self.creator_fee_bps)
+    }
+
+    // each fee is rounded up on its own, same as the program does
+    fn breakdown(&self, sol_amount: u64) -> (u64, u64) {
+        (
+            fee_amount(sol_amount, self.protocol_fee_bps),
+            fee_amount(sol_amount, self.creator_fee_bps),
+        )
+    }
+}
+
+fn fee_amount(amount: u64, fee_bps: u64) -> u64 {
+    ((U128::from(amount) * U128::from(fee_bps) + U128::from(MAX_BPS - 1)) / U128::from(MAX_BPS))
+        .as_u64()
+}
+
+/// Result of a bonding curve quote. `amount_in`/`amount_out` are lamports or token base
+/// units depending on the direction; SOL amounts include fees.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CurveQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub protocol_fee: u64,
+    pub creator_fee: u64,
+    /// price move caused by the trade relative to the spot price, in percent
+    pub price_impact_pct: f64,
+}
+
+impl CurveQuote {
+    pub fn total_fee(&self) -> u64 {
+        self.protocol_fee + self.creator_fee
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub complete: bool,
+}
+
+impl From<&BondingCurveAccount> for BondingCurve {
+    fn from(account: &BondingCurveAccount) -> Self {
+        Self {
+            virtual_token_reserves: account.virtual_token_reserves,
+            virtual_sol_reserves: account.virtual_sol_reserves,
+            real_token_reserves: account.real_token_reserves,
+            real_sol_reserves: account.real_sol_reserves,
+            complete: account.complete,
+        }
+    }
+}
+
+impl BondingCurve {
+    /// Spot price in lamports per token base unit.
+    pub fn spot_price(&self) -> f64 {
+        if self.virtual_token_reserves == 0 {
+            return 0.0;
+        }
+        self.virtual_sol_reserves as f64 / self.virtual_token_reserves as f64
+    }
+
+    fn check_tradable(&self) -> Result<()> {
+        if self.complete {
+            return Err(anyhow!("bonding curve is complete"));
+        }
+        if self.virtual_token_reserves == 0 || self.virtual_sol_reserves == 0 {
+            return Err(anyhow!("invalid bonding curve reserves"));
+        }
+        Ok(())
+    }
+
+    /// Tokens received when spending `sol_in` lamports, fees included.
+    pub fn buy_exact_sol_in(&self, sol_in: u64, fees: &CurveFees) -> Result<CurveQuote> {
+        self.check_tradable()?;
+        // fees are charged on top of the SOL that enters the curve
+        let net_sol = (U128::from(sol_in) * U128::from(MAX_BPS)
+            / U128::from(MAX_BPS + fees.total_bps()))
+        .as_u64();
+        let tokens_out = (U128::from(net_sol) * U128::from(self.virtual_token_reserves)
+            / (U128::from(self.virtual_sol_reserves) + U128::from(net_sol)))
+        .as_u64();
+        if tokens_out > self.real_token_reserves {
+            // the curve only sells what is left, and charges for that
+            return self.buy_exact_tokens_out(self.real_token_reserves, fees);
+        }
+        let (protocol_fee, creator_fee) = fees.breakdown(net_sol);
+        Ok(CurveQuote {
+            amount_in: sol_in,
+            amount_out: tokens_out,
+            protocol_fee,
+            creator_fee,
+            price_impact_pct: buy_price_impact(self.spot_price(), net_sol, tokens_out),
+        })
+    }
+
+    /// Lamports needed to buy exactly `tokens_out`, fees included.
+    pub fn buy_exact_tokens_out(&self, tokens_out: u64, fees: &CurveFees) -> Result<CurveQuote> {
+        self.check_tradable()?;
+        if tokens_out > self.real_token_reserves {
+            return Err(anyhow!(
+                "token amount {} exceeds the bonding curve remaining tokens {}",
+                tokens_out,
+                self.real_token_reserves
+            ));
+        }
+        if tokens_out == 0 {
+            return Ok(CurveQuote::default());
+        }
+        // a curve with more real than virtual tokens is corrupt, never divide by zero on it
+        if tokens_out >= self.virtual_token_reserves {
+            return Err(anyhow!(
+                "token amount {} exceeds the bonding curve virtual tokens {}",
+                tokens_out,
+                self.virtual_token_reserves
+            ));
+        }
+        let sol_cost = (U128::from(tokens_out) * U128::from(self.virtual_sol_reserves)
+            / U128::from(self.virtual_token_reserves - tokens_out))
+        .as_u64()
+            + 1;
+        let (protocol_fee, creator_fee) = fees.breakdown(sol_cost);
+        Ok(CurveQuote {
+            amount_in: sol_cost + protocol_fee + creator_fee,
+            amount_out: tokens_out,
+            protocol_fee,
+            creator_fee,
+            price_impact_pct: buy_price_impact(self.spot_price(), sol_cost, tokens_out),
+        })
+    }
+
+    /// Lamports received when selling `tokens_in`, fees deducted.
+    pub fn sell_exact_tokens_in(&self, tokens_in: u64, fees: &CurveFees) -> Result<CurveQuote> {
+        self.check_tradable()?;
+        let sol_out = (U128::from(tokens_in) * U128::from(self.virtual_sol_reserves)
+            / (U128::from(self.virtual_token_reserves) + U128::from(tokens_in)))
+        .as_u64();
+        let (protocol_fee, creator_fee) = fees.breakdown(sol_out);
+        let amount_out = sol_out
+            .checked_sub(protocol_fee + creator_fee)
+            .ok_or(anyhow!("sell amount is too small to cover pump fees"))?;
+        Ok(CurveQuote {
+            amount_in: tokens_in,
+            amount_out,
+            protocol_fee,
+            creator_fee,
+            price_impact_pct: sell_price_impact(self.spot_price(), sol_out, tokens_in),
+        })
+    }
+
+    /// Tokens needed to receive at least `sol_out` lamports after fees.
+    pub fn sell_exact_sol_out(&self, sol_out: u64, fees: &CurveFees) -> Result<CurveQuote> {
+        self.check_tradable()?;
+        // the fees come from the Global account, a sale must leave something after them
+        let net_bps = MAX_BPS
+            .checked_sub(fees.total_bps())
+            .filter(|net_bps| *net_bps > 0)
+            .ok_or(BondingCurveError::InvalidFees(fees.total_bps()))?;
+        // each fee rounds up by at most one lamport, keep room for both
+        let gross_sol = ((U128::from(sol_out) + U128::from(2)) * U128::from(MAX_BPS)
+            + U128::from(net_bps - 1))
+            / U128::from(net_bps);
+        if gross_sol >= U128::from(self.virtual_sol_reserves)
+            || gross_sol > U128::from(self.real_sol_reserves)
+        {
+            return Err(anyhow!(
+                "sol amount {} exceeds the bonding curve sol reserves {}",
+                sol_out,
+                self.real_sol_reserves
+            ));
+        }
+        let denominator = U128::from(self.virtual_sol_reserves) - gross_sol;
+        let tokens_in = ((gross_sol * U128::from(self.virtual_token_reserves) + denominator
+            - U128::from(1))
+            / denominator)
+            .as_u64();
+        self.sell_exact_tokens_in(tokens_in, fees)
+    }
+
+    /// Lamports (fees included) needed to buy the rest of the curve and graduate it.
+    pub fn sol_to_graduation(&self, fees: &CurveFees) -> Result<u64> {
+        Ok(self
+            .buy_exact_tokens_out(self.real_token_reserves, fees)?
+            .amount_in)
+    }
+
+    /// Share of the sellable supply already bought, in percent.
+    pub fn graduation_progress(&self, initial_real_token_reserves: u64) -> f64 {
+        if self.complete || initial_real_token_reserves == 0 {
+            return 100.0;
+        }
+        let sold = initial_real_token_reserves.saturating_sub(self.real_token_reserves);
+        sold as f64 * 100.0 / initial_real_token_reserves as f64
+    }
+}
+
+fn buy_price_impact(spot_price: f64, sol_amount: u64, token_amount: u64) -> f64 {
+    if spot_price == 0.0 || token_amount == 0 {
+        return 0.0;
+    }
+    let execution_price = sol_amount as f64 / token_amount as f64;
+    // tokens received short of the spot price
+    (1.0 - spot_price / execution_price) * 100.0
+}
+
+fn sell_price_impact(spot_price: f64, sol_amount: u64, token_amount: u64) -> f64 {
+    if spot_price == 0.0 || token_amount == 0 {
+        return 0.0;
+    }
+    let execution_price = sol_amount as f64 / token_amount as f64;
+    (1.0 - execution_price / spot_price) * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEES: CurveFees = CurveFees {
+        protocol_fee_bps: 95,
+        creator_fee_bps: 5,
+    };
+
+    fn curve() -> BondingCurve {
+        BondingCurve {
+            virtual_token_reserves: INITIAL_VIRTUAL_TOKEN_RESERVES,
+            virtual_sol_reserves: INITIAL_VIRTUAL_SOL_RESERVES,
+            real_token_reserves: INITIAL_REAL_TOKEN_RESERVES,
+            real_sol_reserves: 0,
+            complete: false,
+        }
+    }
+
+    #[test]
+    fn test_curve_fees() {
+        let fees = CurveFees::new(95, 5, &Pubkey::default());
+        assert_eq!(fees.creator_fee_bps, 0);
+        assert_eq!(FEES.total_bps(), 100);
+        // rounded up separately
+        assert_eq!(FEES.breakdown(1_000_001), (9_501, 501));
+    }
+
+    #[test]
+    fn test_buy_quotes() {
+        let curve = curve();
+        // 1M tokens at the initial curve: 30e9 * 1e12 / (1.073e15 - 1e12) + 1 = 27_985_075
+        let quote = curve
+            .buy_exact_tokens_out(1_000_000_000_000, &FEES)
+            .unwrap();
+        assert_eq!(quote.protocol_fee, 265_859);
+        assert_eq!(quote.creator_fee, 13_993);
+        assert_eq!(quote.amount_in, 27_985_075 + 265_859 + 13_993);
+        assert!(quote.price_impact_pct > 0.0);
+        assert!(curve
+            .buy_exact_tokens_out(INITIAL_REAL_TOKEN_RESERVES + 1, &FEES)
+            .is_err());
+
+        // spending that cost buys back the same tokens, up to rounding
+        let quote_in = curve.buy_exact_sol_in(quote.amount_in, &FEES).unwrap();
+        assert!(quote_in.amount_out.abs_diff(1_000_000_000_000) < 100_000);
+
+        // more SOL than the curve holds is capped at the remaining tokens
+        let capped = curve.buy_exact_sol_in(1_000_000_000_000, &FEES).unwrap();
+        assert_eq!(capped.amount_out, INITIAL_REAL_TOKEN_RESERVES);
+        assert_eq!(capped.amount_in, curve.sol_to_graduation(&FEES).unwrap());
+
+        // inconsistent reserves are rejected instead of dividing by zero
+        let mut corrupt = curve;
+        corrupt.real_token_reserves = corrupt.virtual_token_reserves;
+        assert!(corrupt
+            .buy_exact_tokens_out(corrupt.virtual_token_reserves, &FEES)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sell_quotes() {
+        let mut curve = curve();
+        curve.real_sol_reserves = 10_000_000_000;
+        let quote = curve
+            .sell_exact_tokens_in(1_000_000_000_000, &FEES)
+            .unwrap();
+        // 30e9 * 1e12 / (1.073e15 + 1e12) = 27_932_960 before fees
+        assert_eq!(quote.protocol_fee, 265_364);
+        assert_eq!(quote.creator_fee, 13_967);
+        assert_eq!(quote.amount_out, 27_932_960 - 265_364 - 13_967);
+        assert!(quote.price_impact_pct > 0.0);
+
+        let quote_out = curve.sell_exact_sol_out(quote.amount_out, &FEES).unwrap();
+        assert!(quote_out.amount_out >= quote.amount_out);
+        assert!(quote_out.amount_in.abs_diff(1_000_000_000_000) < 100_000);
+        assert!(curve.sell_exact_sol_out(20_000_000_000, &FEES).is_err());
+
+        let all_fees = CurveFees {
+            protocol_fee_bps: MAX_BPS,
+            creator_fee_bps: 5,
+        };
+        assert_eq!(
+            curve
+                .sell_exact_sol_out(quote.amount_out, &all_fees)
+                .unwrap_err()
+                .downcast::<BondingCurveError>()
+                .unwrap(),
+            BondingCurveError::InvalidFees(MAX_BPS + 5)
+        );
+    }
+
+    #[test]
+    fn test_price_impact_sign() {
+        // impact is the share of output lost against the spot price, positive for both
+        // directions and the same convention as `quote::price_impact_pct`
+        assert_eq!(buy_price_impact(1.0, 200, 100), 50.0);
+        assert_eq!(sell_price_impact(1.0, 50, 100), 50.0);
+        assert_eq!(
+            buy_price_impact(1.0, 200, 100),
+            crate::quote::price_impact_pct(200, 100, 1.0)
+        );
+        assert_eq!(buy_price_impact(1.0, 100, 100), 0.0);
+        // buying can never lose more than everything
+        assert!(buy_price_impact(1.0, u64::MAX, 1) <= 100.0);
+
+        // a 1 SOL buy on a fresh curve loses net / (virtual sol + net) of the spot value
+        let buy = curve().buy_exact_sol_in(1_000_000_000, &FEES).unwrap();
+        assert!((buy.price_impact_pct - 3.194_888).abs() < 1e-6);
+        // a 1e12 token sell loses tokens / (virtual tokens + tokens) of the spot value
+        let mut sell_curve = curve();
+        sell_curve.real_sol_reserves = 10_000_000_000;
+        let sell = sell_curve
+            .sell_exact_tokens_in(1_000_000_000_000, &FEES)
+            .unwrap();
+        assert!((sell.price_impact_pct - 0.093_113).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_graduation() {
+        let mut curve = curve();
+        assert_eq!(curve.graduation_progress(INITIAL_REAL_TOKEN_RESERVES), 0.0);
+        curve.real_token_reserves = INITIAL_REAL_TOKEN_RESERVES / 2;
+        assert_eq!(curve.graduation_progress(INITIAL_REAL_TOKEN_RESERVES), 50.0);
+        curve.complete = true;
+        assert!(curve.buy_exact_sol_in(1_000_000, &FEES).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use borsh::BorshDeserialize;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use rand::seq::SliceRandom;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use tokio::{sync::RwLock, time::Instant};
+use tracing::{debug, warn};
+
+use super::{
+    curve::{BondingCurve, CurveFees},
+    PUMP_PROGRAM,
+};
+
+pub const PUMP_GLOBAL_DISCRIMINATOR: u64 = 9183522199395952807;
+// how long a fetched Global account is trusted before it is read again
+pub const DEFAULT_GLOBAL_REFRESH_SECS: u64 = 60;
+
+static GLOBAL_ACCOUNT: LazyLock<RwLock<Option<(Instant, GlobalAccount)>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct GlobalAccount {
+    pub discriminator: u64,
+    pub initialized: bool,
+    pub authority: [u8; 32],
+    pub fee_recipient: [u8; 32],
+    pub initial_virtual_token_reserves: u64,
+    pub initial_virtual_sol_reserves: u64,
+    pub initial_real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+    pub withdraw_authority: [u8; 32],
+    pub enable_migrate: bool,
+    pub pool_migration_fee: u64,
+    pub creator_fee_basis_points: u64,
+    pub fee_recipients: [[u8; 32]; 7],
+}
+
+impl GlobalAccount {
+    pub fn fees(&self, creator: &Pubkey) -> CurveFees {
+        CurveFees::new(
+            self.fee_basis_points,
+            self.creator_fee_basis_points,
+            creator,
+        )
+    }
+
+    pub fn fee_recipients(&self) -> Vec<Pubkey> {
+        std::iter::once(&self.fee_recipient)
+            .chain(self.fee_recipients.iter())
+            .map(|recipient| Pubkey::new_from_array(*recipient))
+            .filter(|recipient| *recipient != Pubkey::default())
+            .collect()
+    }
+
+    pub fn random_fee_recipient(&self) -> Result<Pubkey> {
+        self.fee_recipients()
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .ok_or(anyhow!("pump: no fee recipients available"))
+    }
+
+    /// Curve state of a freshly created token.
+    pub fn initial_curve(&self) -> BondingCurve {
+        BondingCurve {
+            virtual_token_reserves: self.initial_virtual_token_reserves,
+            virtual_sol_reserves: self.initial_virtual_sol_reserves,
+            real_token_reserves: self.initial_real_token_reserves,
+            real_sol_reserves: 0,
+            complete: false,
+        }
+    }
+}
+
+pub fn get_global_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"global"], program_id).0
+}
+
+fn refresh_interval() -> Duration {
+    let secs = std::env::var("PUMP_GLOBAL_REFRESH_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_GLOBAL_REFRESH_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Returns the cached Global account, reading it again once the refresh interval has passed.
+pub async fn get_global_account(rpc_client: Arc<RpcClient>) -> Result<GlobalAccount> {
+    if let Some((fetched_at, global)) = GLOBAL_ACCOUNT.read().await.as_ref() {
+        if fetched_at.elapsed() < refresh_interval() {
+            return Ok(global.clone());
+        }
+    }
+    refresh_global_account(rpc_client).await
+}
+
+/// Reads the Global account from chain and replaces the cached copy.
+pub async fn refresh_global_account(rpc_client: Arc<RpcClient>) -> Result<GlobalAccount> {
+    let global_pda = get_global_pda(&Pubkey::from_str(PUMP_PROGRAM)?);
+    let data = rpc_client
+        .get_account_data(&global_pda)
+        .await
+        .inspect_err(|err| {
+            warn!(
+                "Failed to get pump global account data: {}, err: {}",
+                global_pda, err
+            );
+        })?;
+    let global = decode_global_account(&data)?;
+    debug!("pump global account: {:?}", global);
+    *GLOBAL_ACCOUNT.write().await = Some((Instant::now(), global.clone()));
+    Ok(global)
+}
+
+pub fn decode_global_account(data: &[u8]) -> Result<GlobalAccount> {
+    if data.len() < 8 || u64::from_le_bytes(data[..8].try_into()?) != PUMP_GLOBAL_DISCRIMINATOR {
+        return Err(anyhow!("invalid pump global account discriminator"));
+    }
+    // the account keeps growing, only read the prefix we know about
+    GlobalAccount::deserialize(&mut &data[..])
+        .map_err(|e| anyhow!("Failed to deserialize pump global account: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::to_vec;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_global_account() {
+        let recipient = Pubkey::new_unique();
+        let mut fee_recipients = [[0u8; 32]; 7];
+        fee_recipients[3] = Pubkey::new_unique().to_bytes();
+        let global = GlobalAccount {
+            discriminator: PUMP_GLOBAL_DISCRIMINATOR,
+            initialized: true,
+            authority: [1; 32],
+            fee_recipient: recipient.to_bytes(),
+            initial_virtual_token_reserves: 1_073_000_000_000_000,
+            initial_virtual_sol_reserves: 30_000_000_000,
+            initial_real_token_reserves: 793_100_000_000_000,
+            token_total_supply: 1_000_000_000_000_000,
+            fee_basis_points: 95,
+            withdraw_authority: [2; 32],
+            enable_migrate: true,
+            pool_migration_fee: 15_000_001,
+            creator_fee_basis_points: 5,
+            fee_recipients,
+        };
+        let mut data = to_vec(&global).unwrap();
+        // trailing fields we don't know about are ignored
+        data.extend_from_slice(&[9; 64]);
+
+        let decoded = decode_global_account(&data).unwrap();
+        assert_eq!(decoded.fee_basis_points, 95);
+        assert_eq!(decoded.fee_recipients().len(), 2);
+        assert_eq!(decoded.fee_recipients()[0], recipient);
+        assert_eq!(decoded.fees(&Pubkey::new_unique()).total_bps(), 100);
+        assert_eq!(
+            decoded.initial_curve().real_token_reserves,
+            793_100_000_000_000
+        );
+
+        data[0] ^= 1;
+        assert!(decode_global_account(&data).is_err());
+    }
+
+    #[test]
+    fn test_get_global_pda() {
+        assert_eq!(
+            get_global_pda(&Pubkey::from_str(PUMP_PROGRAM).unwrap()).to_string(),
+            "4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf"
+        );
+    }
+}
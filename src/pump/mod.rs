@@ -0,0 +1,684 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use borsh::BorshDeserialize;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+use spl_token::amount_to_ui_amount;
+use spl_token_client::token::TokenError;
+
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    amount::{self, Amount},
+    quote::{self, Quote},
+    slippage::Slippage,
+    swap::{NotFoundPool, SwapDirection, SwapInType, SwapOptions, Venue},
+    token,
+    tx::{self, SendResult},
+};
+
+pub mod curve;
+pub mod global;
+
+use curve::BondingCurve;
+use global::{get_global_account, get_global_pda};
+pub const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const RENT_PROGRAM: &str = "SysvarRent111111111111111111111111111111111";
+pub const ASSOCIATED_TOKEN_PROGRAM: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+pub const PUMP_PROGRAM: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+// pub const PUMP_FUN_MINT_AUTHORITY: &str = "TSLvdd1pWpHVjahSpsvCXUbgwsL3JAcvokwaKt1eokM";
+pub const PUMP_ACCOUNT: &str = "Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1";
+pub const PUMP_BUY_METHOD: u64 = 16927863322537952870;
+pub const PUMP_SELL_METHOD: u64 = 12502976635542562355;
+
+pub struct Pump {
+    pub client: Arc<RpcClient>,
+    pub keypair: Arc<Keypair>,
+}
+
+impl Pump {
+    pub fn new(client: Arc<RpcClient>, keypair: Arc<Keypair>) -> Self {
+        Self { client, keypair }
+    }
+
+    pub async fn swap(
+        &self,
+        mint: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        options: SwapOptions,
+    ) -> Result<SendResult> {
+        let slippage = options.slippage;
+        if matches!(
+            (&swap_direction, &in_type),
+            (SwapDirection::Sell, SwapInType::ExactOut)
+        ) {
+            return Err(anyhow!("exact out sell is not supported in pump fun"));
+        }
+        let owner = self.keypair.pubkey();
+        let mint =
+            Pubkey::from_str(mint).map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        // token or Token-2022, the bonding curve takes both
+        let program_id = token::get_mint_program(self.client.clone(), &mint).await?;
+        let native_mint = spl_token::native_mint::ID;
+
+        let (token_in, token_out, pump_method) = match swap_direction {
+            SwapDirection::Buy => (native_mint, mint, PUMP_BUY_METHOD),
+            SwapDirection::Sell => (mint, native_mint, PUMP_SELL_METHOD),
+        };
+        // wsol is always owned by the token program
+        let (in_program, out_program) = match swap_direction {
+            SwapDirection::Buy => (spl_token::ID, program_id),
+            SwapDirection::Sell => (program_id, spl_token::ID),
+        };
+        let pump_program = Pubkey::from_str(PUMP_PROGRAM)?;
+        let (bonding_curve, associated_bonding_curve, bonding_curve_account) =
+            get_bonding_curve_account(self.client.clone(), &mint, &program_id, &pump_program)
+                .await?;
+        let in_ata = get_associated_token_address_with_program_id(&owner, &token_in, &in_program);
+        let out_ata =
+            get_associated_token_address_with_program_id(&owner, &token_out, &out_program);
+
+        let mut create_instruction = None;
+        let mut close_instruction = None;
+
+        let (amount_specified, amount_ui_pretty) = match swap_direction {
+            SwapDirection::Buy => {
+                // Create base ATA if it doesn't exist.
+                match token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &token_out,
+                    &out_ata,
+                )
+                .await
+                {
+                    Ok(_) => debug!("base ata exists. skipping creation.."),
+                    Err(TokenError::AccountNotFound) | Err(TokenError::AccountInvalidOwner) => {
+                        info!(
+                            "base ATA for mint {} does not exist. will be create",
+                            token_out
+                        );
+                        create_instruction = Some(create_associated_token_account(
+                            &owner,
+                            &owner,
+                            &token_out,
+                            &program_id,
+                        ));
+                    }
+                    Err(error) => error!("error retrieving out ATA: {}", error),
+                }
+
+                match in_type {
+                    SwapInType::ExactOut => {
+                        // amount_in is the exact token quantity to receive
+                        let out_mint = token::get_mint_info(
+                            self.client.clone(),
+                            self.keypair.clone(),
+                            &token_out,
+                        )
+                        .await?;
+                        let amount = amount_in.to_raw(out_mint.base.decimals)?;
+                        (
+                            amount,
+                            (
+                                amount_to_ui_amount(amount, out_mint.base.decimals),
+                                out_mint.base.decimals,
+                            ),
+                        )
+                    }
+                    SwapInType::Usd | SwapInType::Pct => {
+                        return Err(quote::unresolved_buy(&in_type))
+                    }
+                    SwapInType::Qty => {
+                        let amount = amount_in.to_raw(spl_token::native_mint::DECIMALS)?;
+                        (
+                            amount,
+                            (
+                                amount_to_ui_amount(amount, spl_token::native_mint::DECIMALS),
+                                spl_token::native_mint::DECIMALS,
+                            ),
+                        )
+                    }
+                }
+            }
+            SwapDirection::Sell => {
+                let in_account = token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &token_in,
+                    &in_ata,
+                )
+                .await?;
+                let in_mint =
+                    token::get_mint_info(self.client.clone(), self.keypair.clone(), &token_in)
+                        .await?;
+                let amount = match in_type {
+                    SwapInType::Qty => {
+                        let amount = amount_in.to_raw(in_mint.base.decimals)?;
+                        amount::check_balance(amount, in_account.base.amount)?;
+                        amount
+                    }
+                    SwapInType::ExactOut => {
+                        return Err(anyhow!("exact out sell is not supported in pump fun"))
+                    }
+                    SwapInType::Usd => return Err(anyhow!("usd amount only support buy")),
+                    SwapInType::Pct => {
+                        if amount_in.is_all() {
+                            // sell all, close ata
+                            info!("sell all. will be close ATA for mint {}", token_in);
+                            close_instruction = Some(spl_token_2022::instruction::close_account(
+                                &program_id,
+                                &in_ata,
+                                &owner,
+                                &owner,
+                                &[&owner],
+                            )?);
+                            in_account.base.amount
+                        } else {
+                            amount_in.share_of(in_account.base.amount)?
+                        }
+                    }
+                };
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, in_mint.base.decimals),
+                        in_mint.base.decimals,
+                    ),
+                )
+            }
+        };
+
+        info!(
+            "swap: {}, value: {:?} -> {}",
+            token_in, amount_ui_pretty, token_out
+        );
+
+        let curve = BondingCurve::from(&bonding_curve_account);
+        let unit_price = curve.spot_price() / 1000.0;
+
+        let creator = Pubkey::new_from_array(bonding_curve_account.creator);
+        let creator_vault = get_creator_vault_pda(&creator, &pump_program)?;
+        let global = get_global_account(self.client.clone()).await?;
+        let global_pda = get_global_pda(&pump_program);
+        let fee_recipient = global.random_fee_recipient()?;
+        let fees = global.fees(&creator);
+
+        let (token_amount, sol_amount_threshold, input_accouts) = match swap_direction {
+            SwapDirection::Buy => {
+                let (token_amount, max_sol_cost) = match in_type {
+                    SwapInType::ExactOut => {
+                        let quote = curve.buy_exact_tokens_out(amount_specified, &fees)?;
+                        info!("exact out buy quote: {:?}", quote);
+                        (amount_specified, slippage.max_amount(quote.amount_in))
+                    }
+                    SwapInType::Qty | SwapInType::Usd | SwapInType::Pct => {
+                        let quote = curve.buy_exact_sol_in(amount_specified, &fees)?;
+                        info!("buy quote: {:?}", quote);
+                        (quote.amount_out, slippage.max_amount(amount_specified))
+                    }
+                };
+
+                (
+                    token_amount,
+                    max_sol_cost,
+                    vec![
+                        AccountMeta::new_readonly(global_pda, false),
+                        AccountMeta::new(fee_recipient, false),
+                        AccountMeta::new_readonly(mint, false),
+                        AccountMeta::new(bonding_curve, false),
+                        AccountMeta::new(associated_bonding_curve, false),
+                        AccountMeta::new(out_ata, false),
+                        AccountMeta::new(owner, true),
+                        AccountMeta::new_readonly(system_program::id(), false),
+                        AccountMeta::new_readonly(program_id, false),
+                        AccountMeta::new(creator_vault, false),
+                        AccountMeta::new_readonly(Pubkey::from_str(PUMP_ACCOUNT)?, false),
+                        AccountMeta::new_readonly(pump_program, false),
+                    ],
+                )
+            }
+            SwapDirection::Sell => {
+                let quote = curve.sell_exact_tokens_in(amount_specified, &fees)?;
+                info!("sell quote: {:?}", quote);
+                let min_sol_output = slippage.min_amount(quote.amount_out);
+
+                (
+                    amount_specified,
+                    min_sol_output,
+                    vec![
+                        AccountMeta::new_readonly(global_pda, false),
+                        AccountMeta::new(fee_recipient, false),
+                        AccountMeta::new_readonly(mint, false),
+                        AccountMeta::new(bonding_curve, false),
+                        AccountMeta::new(associated_bonding_curve, false),
+                        AccountMeta::new(in_ata, false),
+                        AccountMeta::new(owner, true),
+                        AccountMeta::new_readonly(system_program::id(), false),
+                        AccountMeta::new_readonly(creator_vault, false),
+                        AccountMeta::new_readonly(program_id, false),
+                        AccountMeta::new_readonly(Pubkey::from_str(PUMP_ACCOUNT)?, false),
+                        AccountMeta::new_readonly(pump_program, false),
+                    ],
+                )
+            }
+        };
+
+        info!(
+            "token_amount: {}, sol_amount_threshold: {}, unit_price: {} sol",
+            token_amount, sol_amount_threshold, unit_price
+        );
+
+        let build_swap_instruction = Instruction::new_with_bincode(
+            pump_program,
+            &(pump_method, token_amount, sol_amount_threshold),
+            input_accouts,
+        );
+        // build instructions
+        let mut instructions = vec![];
+        if let Some(create_instruction) = create_instruction {
+            instructions.push(create_instruction);
+        }
+        if amount_specified > 0 {
+            instructions.push(build_swap_instruction)
+        }
+        if let Some(close_instruction) = close_instruction {
+            instructions.push(close_instruction);
+        }
+        if instructions.is_empty() {
+            return Err(anyhow!("instructions is empty, no tx required"));
+        }
+
+        tx::new_signed_and_send(&self.client, &self.keypair, instructions, options.send).await
+    }
+
+    pub async fn quote(
+        &self,
+        mint: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        slippage: Slippage,
+    ) -> Result<Quote> {
+        if matches!(
+            (&swap_direction, &in_type),
+            (SwapDirection::Sell, SwapInType::ExactOut)
+        ) {
+            return Err(anyhow!("exact out sell is not supported in pump fun"));
+        }
+        let mint =
+            Pubkey::from_str(mint).map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let pump_program = Pubkey::from_str(PUMP_PROGRAM)?;
+        let token_program = token::get_mint_program(self.client.clone(), &mint).await?;
+        let (bonding_curve, _, bonding_curve_account) =
+            get_bonding_curve_account(self.client.clone(), &mint, &token_program, &pump_program)
+                .await?;
+        let global = get_global_account(self.client.clone()).await?;
+        let fees = global.fees(&Pubkey::new_from_array(bonding_curve_account.creator));
+        let curve = BondingCurve::from(&bonding_curve_account);
+
+        let (amount_specified, decimals) = quote::resolve_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        let curve_quote = match (&swap_direction, &in_type) {
+            (SwapDirection::Buy, SwapInType::ExactOut) => {
+                curve.buy_exact_tokens_out(amount_specified, &fees)?
+            }
+            (SwapDirection::Buy, _) => curve.buy_exact_sol_in(amount_specified, &fees)?,
+            (SwapDirection::Sell, _) => curve.sell_exact_tokens_in(amount_specified, &fees)?,
+        };
+
+        let mut quote = Quote::new(
+            Venue::Pump,
+            &bonding_curve,
+            &mint,
+            decimals,
+            &swap_direction,
+        );
+        quote.amount_in = curve_quote.amount_in;
+        quote.amount_out = curve_quote.amount_out;
+        quote.price_impact_pct = curve_quote.price_impact_pct;
+        quote.fee_amount = curve_quote.total_fee();
+        (quote.reserve_in, quote.reserve_out) = guard_reserves(&curve, &swap_direction);
+        Ok(quote.with_slippage(slippage, matches!(in_type, SwapInType::ExactOut)))
+    }
+}
+
+// the curve prices against its virtual reserves, but only its real reserves can be
+// traded, so liquidity guards look at those
+fn guard_reserves(curve: &BondingCurve, swap_direction: &SwapDirection) -> (u64, u64) {
+    match swap_direction {
+        SwapDirection::Buy => (curve.real_sol_reserves, curve.real_token_reserves),
+        SwapDirection::Sell => (curve.real_token_reserves, curve.real_sol_reserves),
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaydiumInfo {
+    pub base: f64,
+    pub quote: f64,
+    pub price: f64,
+}
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PumpInfo {
+    pub mint: String,
+    pub bonding_curve: String,
+    pub associated_bonding_curve: String,
+    pub raydium_pool: Option<String>,
+    pub raydium_info: Option<RaydiumInfo>,
+    pub complete: bool,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub total_supply: u64,
+    pub creator: Option<String>,
+    pub is_mayhem_mode: bool,
+    pub is_cashback_coin: bool,
+    /// newest layout the account is long enough to be read as, not the layout it was
+    /// created with: zero padded accounts report the newest layout
+    pub layout: BondingCurveLayout,
+    /// spot price in SOL per token
+    pub price: f64,
+    /// lamports (fees included) needed to buy out the curve
+    pub sol_to_graduation: u64,
+    /// share of the curve supply already sold, in percent
+    pub graduation_progress: f64,
+}
+
+pub const BONDING_CURVE_DISCRIMINATOR: u64 = 6966180631402821399;
+
+/// Bonding curve layouts, each one appends fields to the previous.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BondingCurveLayout {
+    /// reserves, supply and complete flag, 49 bytes
+    #[default]
+    Legacy,
+    /// + creator, 81 bytes
+    Creator,
+    /// + is_mayhem_mode, 82 bytes
+    Mayhem,
+    /// + is_cashback_coin, 83 bytes
+    Cashback,
+}
+
+impl BondingCurveLayout {
+    /// The newest layout whose fields fit in `len` bytes. Newer accounts are allocated with
+    /// zero padding, so a padded account reads as the newest layout and fields it was not
+    /// created with read as their default value.
+    pub fn from_len(len: usize) -> Option<Self> {
+        match len {
+            83.. => Some(Self::Cashback),
+            82 => Some(Self::Mayhem),
+            81 => Some(Self::Creator),
+            49..=80 => Some(Self::Legacy),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BondingCurveError {
+    InvalidDiscriminator(u64),
+    UnknownLayout(usize),
+    Deserialize(String),
+    /// fee bps from the Global account that take the whole trade
+    InvalidFees(u64),
+}
+
+impl std::fmt::Display for BondingCurveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDiscriminator(discriminator) => {
+                write!(f, "invalid bonding curve discriminator: {}", discriminator)
+            }
+            Self::UnknownLayout(len) => {
+                write!(f, "unknown bonding curve layout, account length: {}", len)
+            }
+            Self::Deserialize(err) => {
+                write!(f, "Failed to deserialize bonding curve account: {}", err)
+            }
+            Self::InvalidFees(bps) => {
+                write!(f, "invalid bonding curve fees: {} bps", bps)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BondingCurveError {}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct BondingCurveAccount {
+    pub discriminator: u64,
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    pub complete: bool,
+    /// all zeros for legacy curves without a creator
+    pub creator: [u8; 32],
+    pub is_mayhem_mode: bool,
+    pub is_cashback_coin: bool,
+    /// most fields the account data can be read with, see [`BondingCurveLayout::from_len`]
+    pub layout: BondingCurveLayout,
+}
+
+impl BondingCurveAccount {
+    pub fn decode(data: &[u8]) -> Result<Self, BondingCurveError> {
+        if data.len() < 8 {
+            return Err(BondingCurveError::UnknownLayout(data.len()));
+        }
+        let discriminator = u64::from_le_bytes(data[..8].try_into().unwrap());
+        if discriminator != BONDING_CURVE_DISCRIMINATOR {
+            return Err(BondingCurveError::InvalidDiscriminator(discriminator));
+        }
+        let layout = BondingCurveLayout::from_len(data.len())
+            .ok_or(BondingCurveError::UnknownLayout(data.len()))?;
+
+        fn read<T: BorshDeserialize>(reader: &mut &[u8]) -> Result<T, BondingCurveError> {
+            T::deserialize_reader(reader).map_err(|e| BondingCurveError::Deserialize(e.to_string()))
+        }
+        let mut reader = &data[8..];
+        let mut account = Self {
+            discriminator,
+            virtual_token_reserves: read(&mut reader)?,
+            virtual_sol_reserves: read(&mut reader)?,
+            real_token_reserves: read(&mut reader)?,
+            real_sol_reserves: read(&mut reader)?,
+            token_total_supply: read(&mut reader)?,
+            complete: read(&mut reader)?,
+            layout,
+            ..Default::default()
+        };
+        if layout >= BondingCurveLayout::Creator {
+            account.creator = read(&mut reader)?;
+        }
+        if layout >= BondingCurveLayout::Mayhem {
+            account.is_mayhem_mode = read(&mut reader)?;
+        }
+        if layout >= BondingCurveLayout::Cashback {
+            account.is_cashback_coin = read(&mut reader)?;
+        }
+        Ok(account)
+    }
+}
+
+/// `token_program` owns the mint, see [`token::get_mint_program`].
+pub async fn get_bonding_curve_account(
+    rpc_client: Arc<RpcClient>,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<(Pubkey, Pubkey, BondingCurveAccount)> {
+    let bonding_curve = get_pda(mint, program_id)?;
+    let associated_bonding_curve =
+        get_associated_token_address_with_program_id(&bonding_curve, mint, token_program);
+    let bonding_curve_data = rpc_client
+        .get_account_with_commitment(&bonding_curve, rpc_client.commitment())
+        .await
+        .inspect_err(|err| {
+            warn!(
+                "Failed to get bonding curve account data: {}, err: {}",
+                bonding_curve, err
+            );
+        })?
+        .value
+        .ok_or_else(|| NotFoundPool(format!("bonding curve {} not found", bonding_curve)))?
+        .data;
+
+    let bonding_curve_account = BondingCurveAccount::decode(&bonding_curve_data)?;
+    debug!(
+        "bonding curve {} layout: {:?}",
+        bonding_curve, bonding_curve_account.layout
+    );
+    Ok((
+        bonding_curve,
+        associated_bonding_curve,
+        bonding_curve_account,
+    ))
+}
+
+pub fn get_pda(mint: &Pubkey, program_id: &Pubkey) -> Result<Pubkey> {
+    let seeds = [b"bonding-curve".as_ref(), mint.as_ref()];
+    let (bonding_curve, _bump) = Pubkey::find_program_address(&seeds, program_id);
+    Ok(bonding_curve)
+}
+
+pub fn get_creator_vault_pda(creator: &Pubkey, program_id: &Pubkey) -> Result<Pubkey> {
+    let seeds = [b"creator-vault".as_ref(), creator.as_ref()];
+    let (creator_vault, _bump) = Pubkey::find_program_address(&seeds, program_id);
+    Ok(creator_vault)
+}
+
+// https://frontend-api.pump.fun/coins/8zSLdDzM1XsqnfrHmHvA9ir6pvYDjs8UXz6B2Tydd6b2
+pub async fn get_pump_info(rpc_client: Arc<RpcClient>, mint: &str) -> Result<PumpInfo> {
+    let mint = Pubkey::from_str(mint)?;
+    let program_id = Pubkey::from_str(PUMP_PROGRAM)?;
+    let token_program = token::get_mint_program(rpc_client.clone(), &mint).await?;
+    let (bonding_curve, associated_bonding_curve, bonding_curve_account) =
+        get_bonding_curve_account(rpc_client.clone(), &mint, &token_program, &program_id).await?;
+    let global = get_global_account(rpc_client).await?;
+    let curve = BondingCurve::from(&bonding_curve_account);
+    let fees = global.fees(&Pubkey::new_from_array(bonding_curve_account.creator));
+
+    let pump_info = PumpInfo {
+        mint: mint.to_string(),
+        bonding_curve: bonding_curve.to_string(),
+        associated_bonding_curve: associated_bonding_curve.to_string(),
+        raydium_pool: None,
+        raydium_info: None,
+        complete: bonding_curve_account.complete,
+        virtual_sol_reserves: bonding_curve_account.virtual_sol_reserves,
+        virtual_token_reserves: bonding_curve_account.virtual_token_reserves,
+        real_sol_reserves: bonding_curve_account.real_sol_reserves,
+        real_token_reserves: bonding_curve_account.real_token_reserves,
+        total_supply: bonding_curve_account.token_total_supply,
+        creator: Some(Pubkey::new_from_array(bonding_curve_account.creator))
+            .filter(|creator| *creator != Pubkey::default())
+            .map(|creator| creator.to_string()),
+        is_mayhem_mode: bonding_curve_account.is_mayhem_mode,
+        is_cashback_coin: bonding_curve_account.is_cashback_coin,
+        layout: bonding_curve_account.layout,
+        // pump tokens have 6 decimals
+        price: curve.spot_price() / 1000.0,
+        sol_to_graduation: if curve.complete {
+            0
+        } else {
+            curve.sol_to_graduation(&fees)?
+        },
+        graduation_progress: curve.graduation_progress(global.initial_real_token_reserves),
+    };
+    Ok(pump_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bonding_curve_data(len: usize) -> Vec<u8> {
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_le_bytes().to_vec();
+        for value in [
+            1_073_000_000_000_000u64,
+            30_000_000_000,
+            793_100_000_000_000,
+            0,
+            1_000_000_000_000_000,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(0);
+        data.extend_from_slice(&[7; 32]);
+        data.push(1);
+        data.push(1);
+        data.resize(len, 0);
+        data
+    }
+
+    #[test]
+    fn test_guard_fresh_curve() {
+        let account = BondingCurveAccount::decode(&bonding_curve_data(150)).unwrap();
+        let curve = BondingCurve::from(&account);
+        let mint = Pubkey::new_unique();
+        let mut quote = Quote::new(Venue::Pump, &mint, &mint, 6, &SwapDirection::Buy);
+        (quote.reserve_in, quote.reserve_out) = guard_reserves(&curve, &SwapDirection::Buy);
+        // nothing has been bought yet, the curve holds no SOL
+        let guards = crate::swap::SwapGuards {
+            min_quote_liquidity: Some(1.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            guards.check(&quote),
+            Err(crate::swap::GuardError::Liquidity { liquidity, .. }) if liquidity == 0.0
+        ));
+    }
+
+    #[test]
+    fn test_decode_bonding_curve_account() {
+        let legacy = BondingCurveAccount::decode(&bonding_curve_data(49)).unwrap();
+        assert_eq!(legacy.layout, BondingCurveLayout::Legacy);
+        assert_eq!(legacy.virtual_sol_reserves, 30_000_000_000);
+        assert_eq!(legacy.creator, [0; 32]);
+
+        let creator = BondingCurveAccount::decode(&bonding_curve_data(81)).unwrap();
+        assert_eq!(creator.layout, BondingCurveLayout::Creator);
+        assert_eq!(creator.creator, [7; 32]);
+        assert!(!creator.is_mayhem_mode);
+
+        let padded = BondingCurveAccount::decode(&bonding_curve_data(150)).unwrap();
+        assert_eq!(padded.layout, BondingCurveLayout::Cashback);
+        assert!(padded.is_mayhem_mode);
+        assert!(padded.is_cashback_coin);
+
+        assert_eq!(
+            BondingCurveAccount::decode(&bonding_curve_data(48)),
+            Err(BondingCurveError::UnknownLayout(48))
+        );
+        let mut data = bonding_curve_data(81);
+        data[0] ^= 1;
+        assert!(matches!(
+            BondingCurveAccount::decode(&data),
+            Err(BondingCurveError::InvalidDiscriminator(_))
+        ));
+    }
+}
@@ -0,0 +1,745 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use borsh::BorshDeserialize;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use common::common_utils;
+use rand::seq::SliceRandom;
+use raydium_amm::math::U128;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_program,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account,
+};
+use spl_token::{amount_to_ui_amount, state::Account};
+use spl_token_client::token::TokenError;
+use tracing::{debug, error, info};
+
+use crate::{
+    amount::{self, Amount, MAX_BPS},
+    pump::{ASSOCIATED_TOKEN_PROGRAM, PUMP_BUY_METHOD, PUMP_PROGRAM, PUMP_SELL_METHOD},
+    quote::{self, Quote},
+    slippage::Slippage,
+    swap::{NotFoundPool, SwapDirection, SwapInType, SwapOptions, Venue},
+    token,
+    tx::{self, SendResult},
+};
+
+pub const PUMP_AMM_PROGRAM: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
+pub const PUMP_AMM_POOL_DISCRIMINATOR: u64 = 13577703138238765809;
+pub const PUMP_AMM_GLOBAL_CONFIG_DISCRIMINATOR: u64 = 15686315269655627925;
+// discriminator(8) + pool_bump(1) + index(2) + creator(32)
+const POOL_BASE_MINT_OFFSET: usize = 43;
+const POOL_QUOTE_MINT_OFFSET: usize = 75;
+
+pub struct PumpAmm {
+    pub client: Arc<RpcClient>,
+    pub keypair: Arc<Keypair>,
+    pub pool_id: Option<String>,
+}
+
+impl PumpAmm {
+    pub fn new(client: Arc<RpcClient>, keypair: Arc<Keypair>) -> Self {
+        Self {
+            client,
+            keypair,
+            pool_id: None,
+        }
+    }
+
+    pub fn with_pool_id(&mut self, pool_id: Option<String>) -> &mut Self {
+        self.pool_id = pool_id;
+        self
+    }
+
+    pub async fn swap(
+        &self,
+        mint: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        options: SwapOptions,
+    ) -> Result<SendResult> {
+        let slippage = options.slippage;
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out swap is not supported in pump amm"));
+        }
+        let owner = self.keypair.pubkey();
+        let mint =
+            Pubkey::from_str(mint).map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        // base token program, token or Token-2022
+        let program_id = token::get_mint_program(self.client.clone(), &mint).await?;
+        let native_mint = spl_token::native_mint::ID;
+        let pump_amm_program = Pubkey::from_str(PUMP_AMM_PROGRAM)?;
+
+        let (pool, pool_account) =
+            get_pool(self.client.clone(), self.pool_id.as_deref(), &mint).await?;
+        let global_config = get_global_config_account(self.client.clone()).await?;
+        let base_mint = Pubkey::new_from_array(pool_account.base_mint);
+        let quote_mint = Pubkey::new_from_array(pool_account.quote_mint);
+        if base_mint != mint || quote_mint != native_mint {
+            return Err(
+                NotFoundPool(format!("pump amm pool {} is not a {}/SOL pool", pool, mint)).into(),
+            );
+        }
+        let (base_reserve, quote_reserve) =
+            get_pool_reserves(self.client.clone(), &pool_account).await?;
+        debug!(
+            "pump amm pool: {}, base_reserve: {}, quote_reserve: {}",
+            pool, base_reserve, quote_reserve
+        );
+
+        let (token_in, token_out) = match swap_direction {
+            SwapDirection::Buy => (native_mint, mint),
+            SwapDirection::Sell => (mint, native_mint),
+        };
+        let base_ata =
+            get_associated_token_address_with_program_id(&owner, &base_mint, &program_id);
+
+        let mut create_instruction = None;
+        let mut close_instruction = None;
+
+        let (amount_specified, amount_ui_pretty) = match swap_direction {
+            SwapDirection::Buy => {
+                // Create base ATA if it doesn't exist.
+                match token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &base_mint,
+                    &base_ata,
+                )
+                .await
+                {
+                    Ok(_) => debug!("base ata exists. skipping creation.."),
+                    Err(TokenError::AccountNotFound) | Err(TokenError::AccountInvalidOwner) => {
+                        info!(
+                            "base ATA for mint {} does not exist. will be create",
+                            base_mint
+                        );
+                        create_instruction = Some(create_associated_token_account(
+                            &owner,
+                            &owner,
+                            &base_mint,
+                            &program_id,
+                        ));
+                    }
+                    Err(error) => error!("error retrieving out ATA: {}", error),
+                }
+
+                if matches!(in_type, SwapInType::Usd | SwapInType::Pct) {
+                    return Err(quote::unresolved_buy(&in_type));
+                }
+                let amount = amount_in.to_raw(spl_token::native_mint::DECIMALS)?;
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, spl_token::native_mint::DECIMALS),
+                        spl_token::native_mint::DECIMALS,
+                    ),
+                )
+            }
+            SwapDirection::Sell => {
+                let in_account = token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &base_mint,
+                    &base_ata,
+                )
+                .await?;
+                let in_mint =
+                    token::get_mint_info(self.client.clone(), self.keypair.clone(), &base_mint)
+                        .await?;
+                let amount = match in_type {
+                    SwapInType::Qty => {
+                        let amount = amount_in.to_raw(in_mint.base.decimals)?;
+                        amount::check_balance(amount, in_account.base.amount)?;
+                        amount
+                    }
+                    SwapInType::ExactOut => {
+                        return Err(anyhow!("exact out swap is not supported in pump amm"))
+                    }
+                    SwapInType::Usd => return Err(anyhow!("usd amount only support buy")),
+                    SwapInType::Pct => {
+                        if amount_in.is_all() {
+                            // sell all, close ata
+                            info!("sell all. will be close ATA for mint {}", base_mint);
+                            close_instruction = Some(spl_token_2022::instruction::close_account(
+                                &program_id,
+                                &base_ata,
+                                &owner,
+                                &owner,
+                                &[&owner],
+                            )?);
+                            in_account.base.amount
+                        } else {
+                            amount_in.share_of(in_account.base.amount)?
+                        }
+                    }
+                };
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, in_mint.base.decimals),
+                        in_mint.base.decimals,
+                    ),
+                )
+            }
+        };
+
+        info!(
+            "swap: {}, value: {:?} -> {}",
+            token_in, amount_ui_pretty, token_out
+        );
+
+        let coin_creator = Pubkey::new_from_array(pool_account.coin_creator);
+        let fees = PoolFees::new(&global_config, &coin_creator);
+        let (base_amount, quote_amount_threshold, pump_method) = match swap_direction {
+            SwapDirection::Buy => {
+                let base_amount_out =
+                    buy_quote_input(amount_specified, base_reserve, quote_reserve, &fees)?;
+                let max_quote_amount_in = slippage.max_amount(amount_specified);
+                (base_amount_out, max_quote_amount_in, PUMP_BUY_METHOD)
+            }
+            SwapDirection::Sell => {
+                let quote_amount_out =
+                    sell_base_input(amount_specified, base_reserve, quote_reserve, &fees)?;
+                let min_quote_amount_out = slippage.min_amount(quote_amount_out);
+                (amount_specified, min_quote_amount_out, PUMP_SELL_METHOD)
+            }
+        };
+        info!(
+            "base_amount: {}, quote_amount_threshold: {}",
+            base_amount, quote_amount_threshold
+        );
+
+        // build instructions
+        let mut instructions = vec![];
+        if let Some(create_instruction) = create_instruction {
+            instructions.push(create_instruction);
+        }
+        if amount_specified > 0 {
+            // sol <-> wsol support, the quote side is always a tmp wsol account
+            let seed = &format!("{}", Keypair::new().pubkey())[..32];
+            let wsol_account = Pubkey::create_with_seed(&owner, seed, &spl_token::id())?;
+            let rent = self
+                .client
+                .get_minimum_balance_for_rent_exemption(Account::LEN)
+                .await?;
+            // if buy, fund the max quote amount in
+            let total_amount = match swap_direction {
+                SwapDirection::Buy => rent + quote_amount_threshold,
+                SwapDirection::Sell => rent,
+            };
+            instructions.push(system_instruction::create_account_with_seed(
+                &owner,
+                &wsol_account,
+                &owner,
+                seed,
+                total_amount,
+                Account::LEN as u64,
+                &spl_token::id(),
+            ));
+            instructions.push(spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &wsol_account,
+                &native_mint,
+                &owner,
+            )?);
+
+            let protocol_fee_recipient = global_config.random_protocol_fee_recipient()?;
+            let (coin_creator_vault_authority, coin_creator_vault_ata) =
+                get_coin_creator_vault(&coin_creator, &quote_mint, &pump_amm_program);
+            let input_accounts = vec![
+                AccountMeta::new(pool, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new_readonly(get_global_config_pda(&pump_amm_program), false),
+                AccountMeta::new_readonly(base_mint, false),
+                AccountMeta::new_readonly(quote_mint, false),
+                AccountMeta::new(base_ata, false),
+                AccountMeta::new(wsol_account, false),
+                AccountMeta::new(
+                    Pubkey::new_from_array(pool_account.pool_base_token_account),
+                    false,
+                ),
+                AccountMeta::new(
+                    Pubkey::new_from_array(pool_account.pool_quote_token_account),
+                    false,
+                ),
+                AccountMeta::new_readonly(protocol_fee_recipient, false),
+                AccountMeta::new(
+                    get_associated_token_address(&protocol_fee_recipient, &quote_mint),
+                    false,
+                ),
+                AccountMeta::new_readonly(program_id, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM)?, false),
+                AccountMeta::new_readonly(get_event_authority_pda(&pump_amm_program), false),
+                AccountMeta::new_readonly(pump_amm_program, false),
+                AccountMeta::new(coin_creator_vault_ata, false),
+                AccountMeta::new_readonly(coin_creator_vault_authority, false),
+            ];
+            instructions.push(Instruction::new_with_bincode(
+                pump_amm_program,
+                &(pump_method, base_amount, quote_amount_threshold),
+                input_accounts,
+            ));
+            // close wsol account
+            instructions.push(spl_token::instruction::close_account(
+                &spl_token::id(),
+                &wsol_account,
+                &owner,
+                &owner,
+                &[&owner],
+            )?);
+        }
+        if let Some(close_instruction) = close_instruction {
+            instructions.push(close_instruction);
+        }
+        if instructions.is_empty() {
+            return Err(anyhow!("instructions is empty, no tx required"));
+        }
+
+        tx::new_signed_and_send(&self.client, &self.keypair, instructions, options.send).await
+    }
+
+    pub async fn quote(
+        &self,
+        mint: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        slippage: Slippage,
+    ) -> Result<Quote> {
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out swap is not supported in pump amm"));
+        }
+        let mint =
+            Pubkey::from_str(mint).map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let native_mint = spl_token::native_mint::ID;
+
+        let (pool, pool_account) =
+            get_pool(self.client.clone(), self.pool_id.as_deref(), &mint).await?;
+        let global_config = get_global_config_account(self.client.clone()).await?;
+        if Pubkey::new_from_array(pool_account.base_mint) != mint
+            || Pubkey::new_from_array(pool_account.quote_mint) != native_mint
+        {
+            return Err(
+                NotFoundPool(format!("pump amm pool {} is not a {}/SOL pool", pool, mint)).into(),
+            );
+        }
+        let (base_reserve, quote_reserve) =
+            get_pool_reserves(self.client.clone(), &pool_account).await?;
+        let fees = PoolFees::new(
+            &global_config,
+            &Pubkey::new_from_array(pool_account.coin_creator),
+        );
+
+        let (amount_specified, decimals) = quote::resolve_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        let mut quote = Quote::new(Venue::PumpAmm, &pool, &mint, decimals, &swap_direction);
+        quote.amount_in = amount_specified;
+        match swap_direction {
+            SwapDirection::Buy => {
+                (quote.reserve_in, quote.reserve_out) = (quote_reserve, base_reserve);
+                quote.amount_out =
+                    buy_quote_input(amount_specified, base_reserve, quote_reserve, &fees)?;
+                let effective_quote = effective_quote_amount(amount_specified, &fees);
+                quote.fee_amount = amount_specified - effective_quote;
+                quote.price_impact_pct = quote::price_impact_pct(
+                    effective_quote,
+                    quote.amount_out,
+                    base_reserve as f64 / quote_reserve as f64,
+                );
+            }
+            SwapDirection::Sell => {
+                (quote.reserve_in, quote.reserve_out) = (base_reserve, quote_reserve);
+                quote.amount_out =
+                    sell_base_input(amount_specified, base_reserve, quote_reserve, &fees)?;
+                let gross_quote = gross_quote_amount(amount_specified, base_reserve, quote_reserve);
+                quote.fee_amount = gross_quote - quote.amount_out;
+                quote.price_impact_pct = quote::price_impact_pct(
+                    amount_specified,
+                    gross_quote,
+                    quote_reserve as f64 / base_reserve as f64,
+                );
+            }
+        }
+        Ok(quote.with_slippage(slippage, false))
+    }
+}
+
+/// Fee basis points charged on the quote side of every pump amm trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolFees {
+    pub lp_fee_bps: u64,
+    pub protocol_fee_bps: u64,
+    pub coin_creator_fee_bps: u64,
+}
+
+impl PoolFees {
+    pub fn new(global_config: &GlobalConfigAccount, coin_creator: &Pubkey) -> Self {
+        Self {
+            lp_fee_bps: global_config.lp_fee_basis_points,
+            protocol_fee_bps: global_config.protocol_fee_basis_points,
+            // the creator fee is only charged when the pool has a coin creator
+            coin_creator_fee_bps: if *coin_creator == Pubkey::default() {
+                0
+            } else {
+                global_config.coin_creator_fee_basis_points
+            },
+        }
+    }
+
+    pub fn total_bps(&self) -> u64 {
+        self.lp_fee_bps + self.protocol_fee_bps + self.coin_creator_fee_bps
+    }
+}
+
+/// Base tokens received when spending `quote_amount_in` lamports (fees included).
+pub fn buy_quote_input(
+    quote_amount_in: u64,
+    base_reserve: u64,
+    quote_reserve: u64,
+    fees: &PoolFees,
+) -> Result<u64> {
+    if base_reserve == 0 || quote_reserve == 0 {
+        return Err(anyhow!("invalid pump amm pool reserves"));
+    }
+    let effective_quote = U128::from(effective_quote_amount(quote_amount_in, fees));
+    let base_amount_out =
+        U128::from(base_reserve) * effective_quote / (U128::from(quote_reserve) + effective_quote);
+    Ok(base_amount_out.as_u64())
+}
+
+// fees are charged on top of the quote amount that enters the curve
+fn effective_quote_amount(quote_amount_in: u64, fees: &PoolFees) -> u64 {
+    (U128::from(quote_amount_in) * U128::from(MAX_BPS) / U128::from(MAX_BPS + fees.total_bps()))
+        .as_u64()
+}
+
+// quote amount out of the curve before fees are deducted
+fn gross_quote_amount(base_amount_in: u64, base_reserve: u64, quote_reserve: u64) -> u64 {
+    (U128::from(quote_reserve) * U128::from(base_amount_in)
+        / (U128::from(base_reserve) + U128::from(base_amount_in)))
+    .as_u64()
+}
+
+/// Lamports received when selling `base_amount_in` tokens (fees deducted).
+pub fn sell_base_input(
+    base_amount_in: u64,
+    base_reserve: u64,
+    quote_reserve: u64,
+    fees: &PoolFees,
+) -> Result<u64> {
+    if base_reserve == 0 || quote_reserve == 0 {
+        return Err(anyhow!("invalid pump amm pool reserves"));
+    }
+    let quote_amount_out = gross_quote_amount(base_amount_in, base_reserve, quote_reserve);
+    let fee = [
+        fees.lp_fee_bps,
+        fees.protocol_fee_bps,
+        fees.coin_creator_fee_bps,
+    ]
+    .iter()
+    .map(|bps| fee_amount(quote_amount_out, *bps))
+    .sum::<u64>();
+    quote_amount_out
+        .checked_sub(fee)
+        .ok_or(anyhow!("sell amount is too small to cover pump amm fees"))
+}
+
+// fees are rounded up, same as the program does
+fn fee_amount(amount: u64, fee_bps: u64) -> u64 {
+    ((amount as u128 * fee_bps as u128).div_ceil(MAX_BPS as u128)) as u64
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PumpAmmInfo {
+    pub pool: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+    pub coin_creator: String,
+}
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct PoolAccount {
+    pub discriminator: u64,
+    pub pool_bump: u8,
+    pub index: u16,
+    pub creator: [u8; 32],
+    pub base_mint: [u8; 32],
+    pub quote_mint: [u8; 32],
+    pub lp_mint: [u8; 32],
+    pub pool_base_token_account: [u8; 32],
+    pub pool_quote_token_account: [u8; 32],
+    pub lp_supply: u64,
+    pub coin_creator: [u8; 32],
+}
+
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct GlobalConfigAccount {
+    pub discriminator: u64,
+    pub admin: [u8; 32],
+    pub lp_fee_basis_points: u64,
+    pub protocol_fee_basis_points: u64,
+    pub disable_flags: u8,
+    pub protocol_fee_recipients: [[u8; 32]; 8],
+    pub coin_creator_fee_basis_points: u64,
+}
+
+impl GlobalConfigAccount {
+    pub fn random_protocol_fee_recipient(&self) -> Result<Pubkey> {
+        self.protocol_fee_recipients
+            .iter()
+            .map(|recipient| Pubkey::new_from_array(*recipient))
+            .filter(|recipient| *recipient != Pubkey::default())
+            .collect::<Vec<_>>()
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .ok_or(anyhow!("pump amm: no protocol fee recipients available"))
+    }
+}
+
+fn decode_account<T: BorshDeserialize>(data: &[u8], discriminator: u64, name: &str) -> Result<T> {
+    if data.len() < 8 || u64::from_le_bytes(data[..8].try_into()?) != discriminator {
+        return Err(anyhow!("invalid {} account discriminator", name));
+    }
+    // accounts may be extended with new fields, only read the prefix we know about
+    T::deserialize(&mut &data[..])
+        .map_err(|e| anyhow!("Failed to deserialize {} account: {}", name, e))
+}
+
+pub async fn get_pool_account(rpc_client: Arc<RpcClient>, pool: &Pubkey) -> Result<PoolAccount> {
+    let pool_data = rpc_client.get_account_data(pool).await?;
+    decode_account(&pool_data, PUMP_AMM_POOL_DISCRIMINATOR, "pump amm pool")
+}
+
+/// The pool at `pool_id` when given, otherwise the pool of the mint.
+pub async fn get_pool(
+    rpc_client: Arc<RpcClient>,
+    pool_id: Option<&str>,
+    mint: &Pubkey,
+) -> Result<(Pubkey, PoolAccount)> {
+    if let Some(pool_id) = pool_id {
+        debug!("finding pump amm pool by pool_id: {}", pool_id);
+        let pool_id = Pubkey::from_str(pool_id)?;
+        let pool_account = get_pool_account(rpc_client, &pool_id).await?;
+        Ok((pool_id, pool_account))
+    } else {
+        get_pool_account_by_mint(rpc_client, mint).await
+    }
+}
+
+pub async fn get_pool_account_by_mint(
+    rpc_client: Arc<RpcClient>,
+    mint: &Pubkey,
+) -> Result<(Pubkey, PoolAccount)> {
+    let pump_amm_program = Pubkey::from_str(PUMP_AMM_PROGRAM)?;
+    // pools migrated from the bonding curve live at a canonical address
+    let canonical_pool = get_canonical_pool_pda(mint, &pump_amm_program)?;
+    // only a missing canonical pool falls back to the program scan, rpc errors propagate
+    if let Some(account) = rpc_client
+        .get_account_with_commitment(&canonical_pool, rpc_client.commitment())
+        .await?
+        .value
+    {
+        let pool_account =
+            decode_account(&account.data, PUMP_AMM_POOL_DISCRIMINATOR, "pump amm pool")?;
+        return Ok((canonical_pool, pool_account));
+    }
+
+    debug!("finding pump amm pool by mint: {}", mint);
+    let filters = Some(vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            POOL_BASE_MINT_OFFSET,
+            &mint.to_bytes(),
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            POOL_QUOTE_MINT_OFFSET,
+            &spl_token::native_mint::ID.to_bytes(),
+        )),
+    ]);
+    let pools =
+        crate::rpc::get_program_accounts_with_filters(&rpc_client, pump_amm_program, filters)
+            .await?;
+    let (pool, account) = pools
+        .first()
+        .ok_or_else(|| NotFoundPool("pump amm pool not found".to_string()))?;
+    let pool_account = decode_account(&account.data, PUMP_AMM_POOL_DISCRIMINATOR, "pump amm pool")?;
+    Ok((*pool, pool_account))
+}
+
+pub async fn get_global_config_account(rpc_client: Arc<RpcClient>) -> Result<GlobalConfigAccount> {
+    let global_config = get_global_config_pda(&Pubkey::from_str(PUMP_AMM_PROGRAM)?);
+    let global_config_data = rpc_client.get_account_data(&global_config).await?;
+    decode_account(
+        &global_config_data,
+        PUMP_AMM_GLOBAL_CONFIG_DISCRIMINATOR,
+        "pump amm global config",
+    )
+}
+
+pub async fn get_pool_reserves(
+    rpc_client: Arc<RpcClient>,
+    pool_account: &PoolAccount,
+) -> Result<(u64, u64)> {
+    let load_pubkeys = vec![
+        Pubkey::new_from_array(pool_account.pool_base_token_account),
+        Pubkey::new_from_array(pool_account.pool_quote_token_account),
+    ];
+    let rsps = rpc_client.get_multiple_accounts(&load_pubkeys).await?;
+    let mut reserves = vec![];
+    for rsp in rsps {
+        let account = rsp.ok_or(anyhow!("pump amm pool token account not found"))?;
+        reserves.push(common_utils::unpack_token(&account.data)?.base.amount);
+    }
+    Ok((reserves[0], reserves[1]))
+}
+
+pub async fn get_pump_amm_info(rpc_client: Arc<RpcClient>, mint: &str) -> Result<PumpAmmInfo> {
+    let mint = Pubkey::from_str(mint)?;
+    let (pool, pool_account) = get_pool_account_by_mint(rpc_client.clone(), &mint).await?;
+    let (base_reserve, quote_reserve) = get_pool_reserves(rpc_client, &pool_account).await?;
+
+    Ok(PumpAmmInfo {
+        pool: pool.to_string(),
+        base_mint: Pubkey::new_from_array(pool_account.base_mint).to_string(),
+        quote_mint: Pubkey::new_from_array(pool_account.quote_mint).to_string(),
+        base_reserve,
+        quote_reserve,
+        coin_creator: Pubkey::new_from_array(pool_account.coin_creator).to_string(),
+    })
+}
+
+pub fn get_pool_authority_pda(mint: &Pubkey) -> Result<Pubkey> {
+    let seeds = [b"pool-authority".as_ref(), mint.as_ref()];
+    let (pool_authority, _bump) =
+        Pubkey::find_program_address(&seeds, &Pubkey::from_str(PUMP_PROGRAM)?);
+    Ok(pool_authority)
+}
+
+pub fn get_canonical_pool_pda(mint: &Pubkey, program_id: &Pubkey) -> Result<Pubkey> {
+    let pool_authority = get_pool_authority_pda(mint)?;
+    let index = 0u16.to_le_bytes();
+    let seeds = [
+        b"pool".as_ref(),
+        index.as_ref(),
+        pool_authority.as_ref(),
+        mint.as_ref(),
+        spl_token::native_mint::ID.as_ref(),
+    ];
+    let (pool, _bump) = Pubkey::find_program_address(&seeds, program_id);
+    Ok(pool)
+}
+
+pub fn get_global_config_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"global_config"], program_id).0
+}
+
+pub fn get_event_authority_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], program_id).0
+}
+
+/// Returns the coin creator vault authority and its quote token account.
+pub fn get_coin_creator_vault(
+    coin_creator: &Pubkey,
+    quote_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, Pubkey) {
+    let seeds = [b"creator_vault".as_ref(), coin_creator.as_ref()];
+    let (authority, _bump) = Pubkey::find_program_address(&seeds, program_id);
+    (
+        authority,
+        get_associated_token_address(&authority, quote_mint),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEES: PoolFees = PoolFees {
+        lp_fee_bps: 20,
+        protocol_fee_bps: 5,
+        coin_creator_fee_bps: 5,
+    };
+
+    #[test]
+    fn test_buy_quote_input() {
+        // 1 SOL into a 100 SOL / 1B token pool
+        let base_out =
+            buy_quote_input(1_000_000_000, 1_000_000_000_000_000, 100_000_000_000, &FEES).unwrap();
+        let no_fee = PoolFees {
+            lp_fee_bps: 0,
+            protocol_fee_bps: 0,
+            coin_creator_fee_bps: 0,
+        };
+        let base_out_no_fee = buy_quote_input(
+            1_000_000_000,
+            1_000_000_000_000_000,
+            100_000_000_000,
+            &no_fee,
+        )
+        .unwrap();
+        assert_eq!(base_out_no_fee, 9_900_990_099_009);
+        assert!(base_out < base_out_no_fee);
+    }
+
+    #[test]
+    fn test_sell_base_input() {
+        let quote_out = sell_base_input(
+            10_000_000_000_000,
+            1_000_000_000_000_000,
+            100_000_000_000,
+            &FEES,
+        )
+        .unwrap();
+        // 990099009 before fees, 0.3% fees rounded up per fee
+        assert_eq!(quote_out, 990_099_009 - 1_980_199 - 495_050 - 495_050);
+        assert!(sell_base_input(1, 1, 0, &FEES).is_err());
+    }
+
+    #[test]
+    fn test_pool_fees_without_coin_creator() {
+        let global_config = GlobalConfigAccount {
+            discriminator: PUMP_AMM_GLOBAL_CONFIG_DISCRIMINATOR,
+            admin: [0; 32],
+            lp_fee_basis_points: 20,
+            protocol_fee_basis_points: 5,
+            disable_flags: 0,
+            protocol_fee_recipients: [[0; 32]; 8],
+            coin_creator_fee_basis_points: 5,
+        };
+        assert_eq!(
+            PoolFees::new(&global_config, &Pubkey::default()).total_bps(),
+            25
+        );
+        assert_eq!(
+            PoolFees::new(&global_config, &Pubkey::new_unique()).total_bps(),
+            30
+        );
+        assert!(global_config.random_protocol_fee_recipient().is_err());
+    }
+}
@@ -0,0 +1,702 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token::amount_to_ui_amount;
+use tracing::{info, warn};
+
+use crate::{
+    amount::Amount,
+    api::AppState,
+    constants::{USDC_DECIMALS, USDC_MINT, USDT_MINT},
+    helper, preflight,
+    priority_fee::PriorityFee,
+    pump, pump_amm, raydium, raydium_clmm, raydium_cpmm, router,
+    slippage::Slippage,
+    swap::{SwapDirection, SwapInType, Venue},
+    token::{self, MintTransferFee},
+    tx::SendOptions,
+};
+
+/// What a swap would do, without sending it. Amounts are in raw units of their mint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    pub venue: Venue,
+    pub pool: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub input_decimals: u8,
+    pub output_decimals: u8,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// the swap fails if it would receive less
+    pub min_amount_out: u64,
+    /// the swap fails if it would spend more, only above amount_in for exact out swaps
+    pub max_amount_in: u64,
+    /// quote mint per token
+    pub execution_price: f64,
+    /// output shortfall against the pool spot price, fees excluded, in percent
+    pub price_impact_pct: f64,
+    pub fee_amount: u64,
+    pub fee_mint: String,
+    /// Token-2022 transfer fee withheld from the token side, in raw token units
+    pub transfer_fee: u64,
+    /// pool reserves of the input and output token the price is computed from
+    pub reserve_in: u64,
+    pub reserve_out: u64,
+    /// the side prices and liquidity are counted in, SOL unless a quote mint is requested
+    pub quote_mint: String,
+    /// intermediate mint and its pool with the quote mint, for two hop swaps
+    pub via_mint: Option<String>,
+    pub via_pool: Option<String>,
+}
+
+impl Quote {
+    pub fn new(
+        venue: Venue,
+        pool: &Pubkey,
+        mint: &Pubkey,
+        token_decimals: u8,
+        swap_direction: &SwapDirection,
+    ) -> Self {
+        Self::new_pair(
+            venue,
+            pool,
+            mint,
+            token_decimals,
+            &spl_token::native_mint::ID,
+            spl_token::native_mint::DECIMALS,
+            swap_direction,
+        )
+    }
+
+    /// A quote of the mint against `quote_mint`, buys spend the quote mint.
+    pub fn new_pair(
+        venue: Venue,
+        pool: &Pubkey,
+        mint: &Pubkey,
+        token_decimals: u8,
+        quote_mint: &Pubkey,
+        quote_decimals: u8,
+        swap_direction: &SwapDirection,
+    ) -> Self {
+        let quote = (quote_mint.to_string(), quote_decimals);
+        let token = (mint.to_string(), token_decimals);
+        let (input, output) = match swap_direction {
+            SwapDirection::Buy => (quote, token),
+            SwapDirection::Sell => (token, quote),
+        };
+        Self {
+            venue,
+            pool: pool.to_string(),
+            input_mint: input.0,
+            output_mint: output.0,
+            input_decimals: input.1,
+            output_decimals: output.1,
+            amount_in: 0,
+            amount_out: 0,
+            min_amount_out: 0,
+            max_amount_in: 0,
+            execution_price: 0.0,
+            price_impact_pct: 0.0,
+            fee_amount: 0,
+            fee_mint: quote_mint.to_string(),
+            transfer_fee: 0,
+            reserve_in: 0,
+            reserve_out: 0,
+            quote_mint: quote_mint.to_string(),
+            via_mint: None,
+            via_pool: None,
+        }
+    }
+
+    /// Fills in the slippage bounds and the execution price once the amounts are set.
+    pub fn with_slippage(mut self, slippage: Slippage, exact_out: bool) -> Self {
+        if exact_out {
+            self.min_amount_out = self.amount_out;
+            self.max_amount_in = slippage.max_amount(self.amount_in);
+        } else {
+            self.min_amount_out = slippage.min_amount(self.amount_out);
+            self.max_amount_in = self.amount_in;
+        }
+        let amount_in = amount_to_ui_amount(self.amount_in, self.input_decimals);
+        let amount_out = amount_to_ui_amount(self.amount_out, self.output_decimals);
+        let (quote, token) = if self.input_mint == self.quote_mint {
+            (amount_in, amount_out)
+        } else {
+            (amount_out, amount_in)
+        };
+        self.execution_price = if token > 0.0 { quote / token } else { 0.0 };
+        self
+    }
+
+    /// Takes the token transfer fee out of the quote: buys receive the output net of the fee,
+    /// sells swap the input net of the fee, with the output scaled down linearly. Exact out
+    /// quotes keep the output and gross the input up instead.
+    pub fn with_transfer_fee(
+        mut self,
+        transfer_fee: &MintTransferFee,
+        slippage: Slippage,
+        exact_out: bool,
+    ) -> Self {
+        if transfer_fee.is_empty() {
+            return self;
+        }
+        if exact_out {
+            if self.input_mint == self.quote_mint {
+                // the pool has to send out enough for the requested amount to arrive
+                self.transfer_fee = transfer_fee.inverse_fee(self.amount_out);
+                if self.amount_out > 0 {
+                    self.amount_in =
+                        (self.amount_in as u128 * (self.amount_out + self.transfer_fee) as u128)
+                            .div_ceil(self.amount_out as u128) as u64;
+                }
+            } else {
+                // the pool has to receive the quoted input
+                self.transfer_fee = transfer_fee.inverse_fee(self.amount_in);
+                self.amount_in += self.transfer_fee;
+            }
+            return self.with_slippage(slippage, exact_out);
+        }
+        if self.input_mint == self.quote_mint {
+            self.transfer_fee = transfer_fee.fee(self.amount_out);
+            self.amount_out -= self.transfer_fee;
+        } else if self.amount_in > 0 {
+            self.transfer_fee = transfer_fee.fee(self.amount_in);
+            self.amount_out = (self.amount_out as u128
+                * (self.amount_in - self.transfer_fee) as u128
+                / self.amount_in as u128) as u64;
+        }
+        self.with_slippage(slippage, exact_out)
+    }
+
+    /// Reserve of the quote side of the pool, in units of the quote mint.
+    pub fn quote_liquidity(&self) -> f64 {
+        if self.input_mint == self.quote_mint {
+            amount_to_ui_amount(self.reserve_in, self.input_decimals)
+        } else {
+            amount_to_ui_amount(self.reserve_out, self.output_decimals)
+        }
+    }
+
+    /// Share of the pool output reserve the swap takes, in percent.
+    pub fn reserve_pct(&self) -> f64 {
+        if self.reserve_out == 0 {
+            return 100.0;
+        }
+        self.amount_out as f64 * 100.0 / self.reserve_out as f64
+    }
+}
+
+/// Price impact in percent for `amount_in` (after fees) swapped into `amount_out`,
+/// given the spot price as output per input in raw units.
+pub fn price_impact_pct(amount_in: u64, amount_out: u64, spot_price: f64) -> f64 {
+    if amount_in == 0 || spot_price <= 0.0 {
+        return 0.0;
+    }
+    let execution_price = amount_out as f64 / amount_in as f64;
+    ((1.0 - execution_price / spot_price) * 100.0).max(0.0)
+}
+
+/// Resolves the requested amount to raw units of the side it specifies: lamports for buys
+/// and exact out sells, tokens for exact out buys and sells. Also returns the token decimals.
+pub async fn resolve_amount(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    mint: &Pubkey,
+    amount_in: Amount,
+    swap_direction: &SwapDirection,
+    in_type: &SwapInType,
+) -> Result<(u64, u8)> {
+    resolve_pair_amount(
+        client,
+        keypair,
+        mint,
+        spl_token::native_mint::DECIMALS,
+        amount_in,
+        swap_direction,
+        in_type,
+    )
+    .await
+}
+
+/// Same as [`resolve_amount`] against a quote mint with `quote_decimals`.
+pub async fn resolve_pair_amount(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    mint: &Pubkey,
+    quote_decimals: u8,
+    amount_in: Amount,
+    swap_direction: &SwapDirection,
+    in_type: &SwapInType,
+) -> Result<(u64, u8)> {
+    let (mint_info, mint_program) = token::get_mint_info_with_program(client.clone(), mint).await?;
+    let decimals = mint_info.base.decimals;
+    let amount = match (swap_direction, in_type) {
+        (SwapDirection::Buy, SwapInType::ExactOut) => amount_in.to_raw(decimals)?,
+        (SwapDirection::Buy, SwapInType::Qty) | (SwapDirection::Sell, SwapInType::ExactOut) => {
+            amount_in.to_raw(quote_decimals)?
+        }
+        (SwapDirection::Buy, SwapInType::Usd) | (SwapDirection::Buy, SwapInType::Pct) => {
+            return Err(unresolved_buy(in_type))
+        }
+        (SwapDirection::Sell, SwapInType::Qty) => amount_in.to_raw(decimals)?,
+        (SwapDirection::Sell, SwapInType::Usd) => {
+            return Err(anyhow!("usd amount only support buy"))
+        }
+        (SwapDirection::Sell, SwapInType::Pct) => {
+            let ata = get_associated_token_address_with_program_id(
+                &keypair.pubkey(),
+                mint,
+                &mint_program,
+            );
+            let account = token::get_account_info(client, keypair, mint, &ata).await?;
+            amount_in.share_of(account.base.amount)?
+        }
+    };
+    Ok((amount, decimals))
+}
+
+/// SOL a buy sized in USD or as a share of the balance resolved to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolvedAmount {
+    /// lamports
+    pub amount_in: u64,
+    pub ui_amount_in: f64,
+    /// USD per SOL the amount was converted at, for USD buys
+    pub sol_price: Option<f64>,
+}
+
+/// Resolves a USD or percentage buy of `mint` to the SOL it spends, nothing to resolve for
+/// other swaps. Percentage buys are sized with [`pct_buy_amount`].
+#[allow(clippy::too_many_arguments)]
+pub async fn resolve_buy_amount(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    mint: &str,
+    amount_in: Amount,
+    swap_direction: &SwapDirection,
+    in_type: &SwapInType,
+    quote_mint: &Pubkey,
+    slippage: Slippage,
+    send: &SendOptions,
+) -> Result<Option<ResolvedAmount>> {
+    let (amount, sol_price) = match (swap_direction, in_type) {
+        (SwapDirection::Buy, SwapInType::Usd) | (SwapDirection::Buy, SwapInType::Pct)
+            if *quote_mint != spl_token::native_mint::ID =>
+        {
+            return Err(anyhow!(
+                "usd and pct buys only support the SOL quote mint, not {}",
+                quote_mint
+            ));
+        }
+        (SwapDirection::Buy, SwapInType::Usd) => {
+            let sol_price = sol_usd_price(client).await?;
+            let micro_usd = amount_in.to_raw(USDC_DECIMALS)?;
+            (usd_to_lamports(micro_usd, sol_price), Some(sol_price))
+        }
+        (SwapDirection::Buy, SwapInType::Pct) => {
+            let balance = client.get_balance(&keypair.pubkey()).await?;
+            let overhead = preflight::buy_overhead(client, keypair, mint, send).await?;
+            let reserve = preflight::min_sol_reserve()?;
+            (
+                pct_buy_amount(amount_in, balance, reserve, overhead, slippage)?,
+                None,
+            )
+        }
+        _ => return Ok(None),
+    };
+    if amount == 0 {
+        return Err(anyhow!(
+            "InvalidAmount: {} {:?} resolves to 0 SOL",
+            amount_in,
+            in_type
+        ));
+    }
+    let resolved = ResolvedAmount {
+        amount_in: amount,
+        ui_amount_in: amount_to_ui_amount(amount, spl_token::native_mint::DECIMALS),
+        sol_price,
+    };
+    info!("{} {:?} buy resolved to {:?}", amount_in, in_type, resolved);
+    Ok(Some(resolved))
+}
+
+/// SOL a percentage buy spends: its share of `balance` above `reserve` and the `overhead` the
+/// swap costs on top of the amount, see [`preflight::buy_overhead`]. The route is not known
+/// yet, so the share is left room for the slippage bound the pump venues may take on it.
+pub fn pct_buy_amount(
+    amount_in: Amount,
+    balance: u64,
+    reserve: u64,
+    overhead: u64,
+    slippage: Slippage,
+) -> Result<u64> {
+    let spendable = balance.saturating_sub(reserve).saturating_sub(overhead);
+    Ok(slippage.max_amount_within(amount_in.share_of(spendable)?))
+}
+
+/// Error for a USD or percentage buy that reached a venue without being resolved to SOL with
+/// [`resolve_buy_amount`].
+pub fn unresolved_buy(in_type: &SwapInType) -> anyhow::Error {
+    anyhow!(
+        "InvalidAmount: {:?} buys must be resolved to SOL with resolve_buy_amount first",
+        in_type
+    )
+}
+
+/// Lamports worth `micro_usd` (USD with 6 decimals) at `sol_price` USD per SOL, rounded down.
+pub fn usd_to_lamports(micro_usd: u64, sol_price: f64) -> u64 {
+    if sol_price <= 0.0 {
+        return 0;
+    }
+    (micro_usd as f64 * 1_000.0 / sol_price) as u64
+}
+
+/// USD per SOL from the deepest SOL/USDC Raydium AMM v4 pool, the pump.fun SOL price when
+/// no pool can be read.
+pub async fn sol_usd_price(client: Arc<RpcClient>) -> Result<f64> {
+    let sol = spl_token::native_mint::ID.to_string();
+    match raydium::get_ranked_pools(client, &sol, &Pubkey::from_str(USDC_MINT)?).await {
+        Ok(pools) => {
+            if let Some(pool) = pools
+                .iter()
+                .find(|pool| pool.liquidity.tradable && pool.liquidity.token_reserve > 0)
+            {
+                let usdc = amount_to_ui_amount(pool.liquidity.quote_reserve, USDC_DECIMALS);
+                let sol = amount_to_ui_amount(
+                    pool.liquidity.token_reserve,
+                    spl_token::native_mint::DECIMALS,
+                );
+                return Ok(usdc / sol);
+            }
+            warn!("no tradable SOL/USDC pool, using the pump.fun SOL price");
+        }
+        Err(err) => warn!(
+            "SOL/USDC pool not found: {}, using the pump.fun SOL price",
+            err
+        ),
+    }
+    helper::get_solana_price().await
+}
+
+/// Parses a quote mint given as `sol`, `usdc`, `usdt` or a mint address.
+pub fn parse_quote_mint(quote_mint: &str) -> Result<Pubkey> {
+    let quote_mint = match quote_mint.to_lowercase().as_str() {
+        "sol" | "wsol" => spl_token::native_mint::ID.to_string(),
+        "usdc" => USDC_MINT.to_string(),
+        "usdt" => USDT_MINT.to_string(),
+        _ => quote_mint.to_string(),
+    };
+    Pubkey::from_str(&quote_mint).map_err(|e| anyhow!("invalid quote mint {}: {}", quote_mint, e))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn quote(
+    state: AppState,
+    mint: &str,
+    amount_in: Amount,
+    swap_direction: SwapDirection,
+    in_type: SwapInType,
+    slippage: Slippage,
+    quote_mint: Pubkey,
+) -> Result<Quote> {
+    let client = state.client.clone();
+
+    // quotes are sized as a plain rpc send at the default priority fee
+    let (amount_in, in_type) = match resolve_buy_amount(
+        client.clone(),
+        state.wallet.clone(),
+        mint,
+        amount_in,
+        &swap_direction,
+        &in_type,
+        &quote_mint,
+        slippage,
+        &SendOptions::new(false, PriorityFee::default()),
+    )
+    .await?
+    {
+        Some(resolved) => (Amount::Raw(resolved.amount_in), SwapInType::Qty),
+        None => (amount_in, in_type),
+    };
+    let route = router::best_route(
+        client,
+        state.wallet,
+        mint,
+        amount_in,
+        swap_direction,
+        in_type,
+        slippage,
+        quote_mint,
+    )
+    .await?;
+    for losing_quote in &route.losing_quotes {
+        info!(
+            "{} pool {} quoted {} out for {} in",
+            losing_quote.venue, losing_quote.pool, losing_quote.amount_out, losing_quote.amount_in
+        );
+    }
+    Ok(route.quote)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn quote_venue(
+    client: Arc<RpcClient>,
+    wallet: Arc<Keypair>,
+    venue: Venue,
+    pool_id: Option<String>,
+    mint: &str,
+    amount_in: Amount,
+    swap_direction: SwapDirection,
+    in_type: SwapInType,
+    slippage: Slippage,
+    quote_mint: Pubkey,
+) -> Result<Quote> {
+    if venue != Venue::RaydiumAmm && quote_mint != spl_token::native_mint::ID {
+        return Err(anyhow!(
+            "quote mint {} is only supported on raydium amm v4 pools",
+            quote_mint
+        ));
+    }
+    let exact_out = matches!(in_type, SwapInType::ExactOut);
+    let transfer_fee =
+        token::get_transfer_fee(client.clone(), wallet.clone(), &Pubkey::from_str(mint)?).await?;
+    let quote = match venue {
+        Venue::Pump => {
+            let swapx = pump::Pump::new(client, wallet);
+            swapx
+                .quote(mint, amount_in, swap_direction, in_type, slippage)
+                .await
+        }
+        Venue::PumpAmm => {
+            let mut swapx = pump_amm::PumpAmm::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .quote(mint, amount_in, swap_direction, in_type, slippage)
+                .await
+        }
+        Venue::RaydiumAmm => {
+            let mut swapx = raydium::Raydium::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .with_quote_mint(Some(quote_mint))
+                .quote(mint, amount_in, swap_direction, in_type, slippage)
+                .await
+        }
+        Venue::RaydiumCpmm => {
+            let mut swapx = raydium_cpmm::RaydiumCpmm::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .quote(mint, amount_in, swap_direction, in_type, slippage)
+                .await
+        }
+        Venue::RaydiumClmm => {
+            let mut swapx = raydium_clmm::RaydiumClmm::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .quote(mint, amount_in, swap_direction, in_type, slippage)
+                .await
+        }
+    }?;
+    Ok(quote.with_transfer_fee(&transfer_fee, slippage, exact_out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preflight::SwapCost;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_quote_with_slippage() {
+        let mint = Pubkey::new_unique();
+        let mut quote = Quote::new(
+            Venue::RaydiumAmm,
+            &Pubkey::new_unique(),
+            &mint,
+            6,
+            &SwapDirection::Buy,
+        );
+        quote.amount_in = 1_000_000_000;
+        quote.amount_out = 2_000_000_000;
+        let exact_in = quote
+            .clone()
+            .with_slippage(Slippage::from_bps(500).unwrap(), false);
+        assert_eq!(exact_in.min_amount_out, 1_900_000_000);
+        assert_eq!(exact_in.max_amount_in, 1_000_000_000);
+        // 1 SOL for 2000 tokens
+        assert_eq!(exact_in.execution_price, 0.0005);
+
+        let exact_out = quote.with_slippage(Slippage::from_bps(500).unwrap(), true);
+        assert_eq!(exact_out.min_amount_out, 2_000_000_000);
+        assert_eq!(exact_out.max_amount_in, 1_050_000_000);
+    }
+
+    #[test]
+    fn test_quote_pair() {
+        let usdc = parse_quote_mint("USDC").unwrap();
+        assert_eq!(usdc.to_string(), USDC_MINT);
+        assert_eq!(parse_quote_mint("sol").unwrap(), spl_token::native_mint::ID);
+        assert!(parse_quote_mint("not a mint").is_err());
+
+        let mut quote = Quote::new_pair(
+            Venue::RaydiumAmm,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            6,
+            &usdc,
+            6,
+            &SwapDirection::Sell,
+        );
+        quote.amount_in = 1_000_000_000;
+        quote.amount_out = 20_000_000;
+        quote.reserve_in = 100_000_000_000;
+        quote.reserve_out = 2_500_000_000;
+        let quote = quote.with_slippage(Slippage::from_bps(100).unwrap(), false);
+        assert_eq!(quote.output_mint, USDC_MINT);
+        // 20 USDC for 1000 tokens
+        assert_eq!(quote.execution_price, 0.02);
+        assert_eq!(quote.quote_liquidity(), 2500.0);
+    }
+
+    #[test]
+    fn test_quote_transfer_fee() {
+        use spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeConfig};
+
+        // 1% on every transfer
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: 100.into(),
+        };
+        let transfer_fee = MintTransferFee {
+            epoch: 0,
+            config: Some(TransferFeeConfig {
+                older_transfer_fee: fee,
+                newer_transfer_fee: fee,
+                ..Default::default()
+            }),
+        };
+        let quote = |swap_direction| {
+            let mut quote = Quote::new(
+                Venue::RaydiumCpmm,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                6,
+                &swap_direction,
+            );
+            quote.amount_in = 1_000_000_000;
+            quote.amount_out = 2_000_000_000;
+            quote
+        };
+
+        let buy = quote(SwapDirection::Buy).with_transfer_fee(
+            &transfer_fee,
+            Slippage::from_bps(0).unwrap(),
+            false,
+        );
+        assert_eq!(buy.transfer_fee, 20_000_000);
+        assert_eq!(buy.amount_out, 1_980_000_000);
+        assert_eq!(buy.min_amount_out, 1_980_000_000);
+
+        let sell = quote(SwapDirection::Sell).with_transfer_fee(
+            &transfer_fee,
+            Slippage::from_bps(0).unwrap(),
+            false,
+        );
+        assert_eq!(sell.transfer_fee, 10_000_000);
+        assert_eq!(sell.amount_out, 1_980_000_000);
+
+        // exact out keeps the requested output and grosses up the input
+        let buy = quote(SwapDirection::Buy).with_transfer_fee(
+            &transfer_fee,
+            Slippage::from_bps(100).unwrap(),
+            true,
+        );
+        assert_eq!(buy.transfer_fee, 20_202_021);
+        assert_eq!(buy.amount_out, 2_000_000_000);
+        assert_eq!(buy.min_amount_out, 2_000_000_000);
+        assert_eq!(buy.amount_in, 1_010_101_011);
+        assert_eq!(buy.max_amount_in, 1_020_202_022);
+
+        let sell = quote(SwapDirection::Sell).with_transfer_fee(
+            &transfer_fee,
+            Slippage::from_bps(0).unwrap(),
+            true,
+        );
+        assert_eq!(sell.transfer_fee, 10_101_011);
+        assert_eq!(sell.amount_in, 1_010_101_011);
+        assert_eq!(sell.max_amount_in, 1_010_101_011);
+        assert_eq!(sell.amount_out, 2_000_000_000);
+
+        let no_fee = quote(SwapDirection::Sell).with_transfer_fee(
+            &MintTransferFee::default(),
+            Slippage::from_bps(0).unwrap(),
+            false,
+        );
+        assert_eq!(no_fee.amount_out, 2_000_000_000);
+    }
+
+    #[test]
+    fn test_pct_buy_amount() {
+        let slippage = Slippage::from_bps(500).unwrap();
+        let balance = 2_000_000_000;
+        let cost = SwapCost {
+            wsol_rent: 2_039_280,
+            fee: 4_005_000,
+            reserve: 10_000_000,
+            ..SwapCost::default()
+        };
+        let overhead = cost.wsol_rent + cost.ata_rent + cost.fee + cost.jito_tip;
+        let all = Amount::new(Decimal::ONE, false).unwrap();
+        let amount = pct_buy_amount(all, balance, cost.reserve, overhead, slippage).unwrap();
+        assert_eq!(amount, 1_889_481_638);
+
+        // a 100% buy on PumpSwap may take the padded amount and still fits the balance exactly
+        let mut quote = Quote::new(
+            Venue::PumpAmm,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            6,
+            &SwapDirection::Buy,
+        );
+        quote.amount_in = amount;
+        let quote = quote.with_slippage(slippage, false);
+        let cost = SwapCost {
+            amount: preflight::max_sol_in(&quote, slippage),
+            ..cost
+        };
+        assert_eq!(cost.total(), balance);
+        assert!(cost.check(balance).is_ok());
+
+        let half = Amount::new(Decimal::new(5, 1), false).unwrap();
+        assert_eq!(
+            pct_buy_amount(half, balance, cost.reserve, overhead, slippage).unwrap(),
+            944_740_819
+        );
+        assert_eq!(
+            pct_buy_amount(all, 1_000_000, cost.reserve, overhead, slippage).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_usd_to_lamports() {
+        // $15 at $150 per SOL
+        assert_eq!(usd_to_lamports(15_000_000, 150.0), 100_000_000);
+        assert_eq!(usd_to_lamports(1, 150.0), 6);
+        assert_eq!(usd_to_lamports(15_000_000, 0.0), 0);
+    }
+
+    #[test]
+    fn test_price_impact_pct() {
+        // constant product: 10 in against 1000/1000 reserves gives 9 out
+        let impact = price_impact_pct(10, 9, 1.0);
+        assert!((impact - 10.0).abs() < 1e-9);
+        assert_eq!(price_impact_pct(0, 0, 1.0), 0.0);
+    }
+}
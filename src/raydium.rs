@@ -2,14 +2,21 @@ use std::env;
 
 use amm_cli::AmmSwapInfoResult;
 use anyhow::{anyhow, Context, Result};
-use raydium_amm::state::{AmmInfo, Loadable};
+use borsh::BorshDeserialize;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use common::common_utils;
+use raydium_amm::{
+    math::U128,
+    state::{AmmInfo, Loadable},
+};
 use reqwest::Proxy;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use solana_client::{
-    rpc_client::RpcClient,
+    nonblocking::rpc_client::RpcClient,
     rpc_filter::{Memcmp, RpcFilterType},
 };
 use solana_sdk::{
+    commitment_config::CommitmentConfig,
     // native_token::LAMPORTS_PER_SOL,
     instruction::Instruction,
     program_pack::Pack,
@@ -19,26 +26,38 @@ use solana_sdk::{
     system_instruction,
 };
 use spl_associated_token_account::{
-    get_associated_token_address, instruction::create_associated_token_account,
+    get_associated_token_address,
+    instruction::{create_associated_token_account, create_associated_token_account_idempotent},
 };
-use spl_token::{amount_to_ui_amount, ui_amount_to_amount};
+use spl_token::amount_to_ui_amount;
 use spl_token_client::token::TokenError;
 use std::{str::FromStr, sync::Arc};
+use tokio::runtime::Handle;
 
 use crate::{
-    swap::{SwapDirection, SwapInType},
-    token, tx,
+    amount::{self, Amount},
+    constants::HOP_MINTS,
+    quote::{self, Quote},
+    rpc,
+    slippage::Slippage,
+    swap::{NotFoundPool, SwapDirection, SwapInType, SwapOptions, Venue},
+    token,
+    tx::{self, Leftover, SendResult},
 };
 use spl_token::state::Account;
 
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub const AMM_PROGRAM: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+// signs for the vaults of every AMM v4 pool
+pub const AMM_AUTHORITY: &str = "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1";
 
 pub struct Raydium {
     pub client: Arc<RpcClient>,
     pub keypair: Arc<Keypair>,
     pub pool_id: Option<String>,
+    /// the mint buys spend and sells receive, SOL by default
+    pub quote_mint: Pubkey,
 }
 
 impl Raydium {
@@ -47,6 +66,7 @@ impl Raydium {
             client,
             keypair,
             pool_id: None,
+            quote_mint: spl_token::native_mint::ID,
         }
     }
 
@@ -55,35 +75,120 @@ impl Raydium {
         self
     }
 
+    pub fn with_quote_mint(&mut self, quote_mint: Option<Pubkey>) -> &mut Self {
+        self.quote_mint = quote_mint.unwrap_or(spl_token::native_mint::ID);
+        self
+    }
+
+    /// The configured pool, else the deepest pool pairing the mint with the quote mint. A
+    /// token without a SOL pool falls back to its first stable pool, which callers swap
+    /// through in two hops.
+    async fn find_pool(&self, mint: &str) -> Result<(Pubkey, AmmInfo)> {
+        if self.pool_id.is_some() {
+            return get_pool_state(self.client.clone(), self.pool_id.as_deref(), Some(mint)).await;
+        }
+        if self.quote_mint != spl_token::native_mint::ID {
+            return get_pool_state_by_pair(self.client.clone(), mint, &self.quote_mint).await;
+        }
+        let err = match get_pool_state(self.client.clone(), None, Some(mint)).await {
+            Ok(pool) => return Ok(pool),
+            Err(err) if NotFoundPool::is(&err) => err,
+            Err(err) => return Err(err),
+        };
+        for hop_mint in HOP_MINTS {
+            let hop_mint = Pubkey::from_str(hop_mint)?;
+            match get_pool_state_by_pair(self.client.clone(), mint, &hop_mint).await {
+                Ok(pool) => return Ok(pool),
+                Err(err) if NotFoundPool::is(&err) => {
+                    debug!("no {} pool for {}: {}", hop_mint, mint, err)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(err)
+    }
+
+    async fn mint_decimals(&self, mint: &Pubkey) -> Result<u8> {
+        if *mint == spl_token::native_mint::ID {
+            return Ok(spl_token::native_mint::DECIMALS);
+        }
+        let mint_info =
+            token::get_mint_info(self.client.clone(), self.keypair.clone(), mint).await?;
+        Ok(mint_info.base.decimals)
+    }
+
+    /// Instructions creating a temporary wsol account holding `lamports` above rent, to be
+    /// closed after the swap.
+    async fn wsol_account_instructions(&self, lamports: u64) -> Result<(Pubkey, Vec<Instruction>)> {
+        let owner = self.keypair.pubkey();
+        let seed = &format!("{}", Keypair::new().pubkey())[..32];
+        let wsol_pubkey = Pubkey::create_with_seed(&owner, seed, &spl_token::id())?;
+        let rent = self
+            .client
+            .get_minimum_balance_for_rent_exemption(Account::LEN)
+            .await?;
+        let instructions = vec![
+            system_instruction::create_account_with_seed(
+                &owner,
+                &wsol_pubkey,
+                &owner,
+                seed,
+                rent + lamports,
+                Account::LEN as u64, // 165, // Token account size
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &wsol_pubkey,
+                &spl_token::native_mint::ID,
+                &owner,
+            )?,
+        ];
+        Ok((wsol_pubkey, instructions))
+    }
+
     pub async fn swap(
         &self,
         mint_str: &str,
-        amount_in: f64,
+        amount_in: Amount,
         swap_direction: SwapDirection,
         in_type: SwapInType,
-        slippage: u64,
-        use_jito: bool,
-    ) -> Result<Vec<String>> {
-        // slippage_bps = 50u64; // 0.5%
-        let slippage_bps = slippage * 100;
+        options: SwapOptions,
+    ) -> Result<SendResult> {
+        let slippage = options.slippage;
         let owner = self.keypair.pubkey();
         let mint = Pubkey::from_str(mint_str)
             .map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
         let program_id = spl_token::ID;
         let native_mint = spl_token::native_mint::ID;
+        let quote_mint = self.quote_mint;
 
-        let (amm_pool_id, pool_state) =
-            get_pool_state(self.client.clone(), self.pool_id.as_deref(), Some(mint_str)).await?;
+        let (amm_pool_id, pool_state) = self.find_pool(mint_str).await?;
         // debug!("pool_state: {:#?}", pool_state);
+        let pair_mint = pair_mint(&pool_state, &mint);
+        if pair_mint != quote_mint {
+            return self
+                .swap_two_hop(
+                    mint,
+                    (amm_pool_id, pool_state),
+                    amount_in,
+                    swap_direction,
+                    in_type,
+                    options,
+                )
+                .await;
+        }
 
-        let (token_in, token_out, user_input_token, swap_base_in) = match (
+        // exact out swaps specify the amount to receive, the input is capped by slippage
+        let swap_base_in = !matches!(in_type, SwapInType::ExactOut);
+        let (token_in, token_out, user_input_token) = match (
             swap_direction.clone(),
-            pool_state.coin_vault_mint == native_mint,
+            pool_state.coin_vault_mint == quote_mint,
         ) {
-            (SwapDirection::Buy, true) => (native_mint, mint, pool_state.coin_vault, true),
-            (SwapDirection::Buy, false) => (native_mint, mint, pool_state.pc_vault, true),
-            (SwapDirection::Sell, true) => (mint, native_mint, pool_state.pc_vault, true),
-            (SwapDirection::Sell, false) => (mint, native_mint, pool_state.coin_vault, true),
+            (SwapDirection::Buy, true) => (quote_mint, mint, pool_state.coin_vault),
+            (SwapDirection::Buy, false) => (quote_mint, mint, pool_state.pc_vault),
+            (SwapDirection::Sell, true) => (mint, quote_mint, pool_state.pc_vault),
+            (SwapDirection::Sell, false) => (mint, quote_mint, pool_state.coin_vault),
         };
 
         debug!("token_in:{token_in}, token_out:{token_out}, user_input_token:{user_input_token}, swap_base_in:{swap_base_in}");
@@ -93,6 +198,8 @@ impl Raydium {
 
         let mut create_instruction = None;
         let mut close_instruction = None;
+        // token balance an exact out sell is checked against once its max input is known
+        let mut exact_out_balance = None;
 
         let (amount_specified, amount_ui_pretty) = match swap_direction {
             SwapDirection::Buy => {
@@ -128,12 +235,46 @@ impl Raydium {
                     Err(error) => error!("error retrieving out ATA: {}", error),
                 }
 
-                (
-                    ui_amount_to_amount(amount_in, spl_token::native_mint::DECIMALS),
-                    (amount_in, spl_token::native_mint::DECIMALS),
-                )
+                match in_type {
+                    SwapInType::ExactOut => {
+                        let out_mint = token::get_mint_info(
+                            self.client.clone(),
+                            self.keypair.clone(),
+                            &token_out,
+                        )
+                        .await?;
+                        let amount = amount_in.to_raw(out_mint.base.decimals)?;
+                        (
+                            amount,
+                            (
+                                amount_to_ui_amount(amount, out_mint.base.decimals),
+                                out_mint.base.decimals,
+                            ),
+                        )
+                    }
+                    SwapInType::Usd | SwapInType::Pct => {
+                        return Err(quote::unresolved_buy(&in_type))
+                    }
+                    SwapInType::Qty => {
+                        let quote_decimals = self.mint_decimals(&quote_mint).await?;
+                        let amount = amount_in.to_raw(quote_decimals)?;
+                        (
+                            amount,
+                            (amount_to_ui_amount(amount, quote_decimals), quote_decimals),
+                        )
+                    }
+                }
             }
             SwapDirection::Sell => {
+                if quote_mint != native_mint {
+                    // receive into the quote mint ata, wsol goes through a temporary account
+                    create_instruction = Some(create_associated_token_account_idempotent(
+                        &owner,
+                        &owner,
+                        &quote_mint,
+                        &program_id,
+                    ));
+                }
                 let in_account = token::get_account_info(
                     self.client.clone(),
                     self.keypair.clone(),
@@ -144,11 +285,21 @@ impl Raydium {
                 let in_mint =
                     token::get_mint_info(self.client.clone(), self.keypair.clone(), &token_in)
                         .await?;
+                let quote_decimals = self.mint_decimals(&quote_mint).await?;
                 let amount = match in_type {
-                    SwapInType::Qty => ui_amount_to_amount(amount_in, in_mint.base.decimals),
+                    SwapInType::Qty => {
+                        let amount = amount_in.to_raw(in_mint.base.decimals)?;
+                        amount::check_balance(amount, in_account.base.amount)?;
+                        amount
+                    }
+                    SwapInType::ExactOut => {
+                        // receive exactly amount_in of the quote mint
+                        exact_out_balance = Some(in_account.base.amount);
+                        amount_in.to_raw(quote_decimals)?
+                    }
+                    SwapInType::Usd => return Err(anyhow!("usd amount only support buy")),
                     SwapInType::Pct => {
-                        let amount_in_pct = amount_in.min(1.0);
-                        if amount_in_pct == 1.0 {
+                        if amount_in.is_all() {
                             // sell all, close ata
                             info!("sell all. will be close ATA for mint {}", token_in);
                             close_instruction = Some(spl_token::instruction::close_account(
@@ -156,83 +307,73 @@ impl Raydium {
                                 &in_ata,
                                 &owner,
                                 &owner,
-                                &vec![&owner],
+                                &[&owner],
                             )?);
                             in_account.base.amount
                         } else {
-                            (amount_in_pct * 100.0) as u64 * in_account.base.amount / 100
+                            amount_in.share_of(in_account.base.amount)?
                         }
                     }
                 };
-                (
-                    amount,
-                    (
-                        amount_to_ui_amount(amount, in_mint.base.decimals),
-                        in_mint.base.decimals,
-                    ),
-                )
+                let decimals = match in_type {
+                    SwapInType::ExactOut => quote_decimals,
+                    _ => in_mint.base.decimals,
+                };
+                (amount, (amount_to_ui_amount(amount, decimals), decimals))
             }
         };
 
         let amm_program = Pubkey::from_str(AMM_PROGRAM)?;
         debug!("amm pool id: {amm_pool_id}");
 
-        let swap_info_result = amm_cli::calculate_swap_info(
+        let swap_info_result = calculate_swap_info(
             &self.client,
             amm_program,
             amm_pool_id,
             user_input_token,
             amount_specified,
-            slippage_bps,
+            slippage.bps(),
             swap_base_in,
-        )?;
+        )
+        .await?;
         let other_amount_threshold = swap_info_result.other_amount_threshold;
+        if let Some(balance) = exact_out_balance {
+            // the most tokens the sell can take
+            amount::check_balance(other_amount_threshold, balance)?;
+        }
 
         info!("swap_info_result: {:#?}", swap_info_result);
 
-        info!(
-            "swap: {}, value: {:?} -> {}",
-            token_in, amount_ui_pretty, token_out
-        );
+        if swap_base_in {
+            info!(
+                "swap: {}, value: {:?} -> {}",
+                token_in, amount_ui_pretty, token_out
+            );
+        } else {
+            info!(
+                "swap: {} -> {}, exact out value: {:?}",
+                token_in, token_out, amount_ui_pretty
+            );
+        }
         // build instructions
         let mut instructions = vec![];
         // sol <-> wsol support
         let mut wsol_account = None;
         if token_in == native_mint || token_out == native_mint {
-            // create wsol account
-            let seed = &format!("{}", Keypair::new().pubkey())[..32];
-            let wsol_pubkey = Pubkey::create_with_seed(&owner, seed, &spl_token::id())?;
-            wsol_account = Some(wsol_pubkey);
-
-            // LAMPORTS_PER_SOL / 100 // 0.01 SOL as rent
-            // get rent
-            let rent = self
-                .client
-                .get_minimum_balance_for_rent_exemption(Account::LEN)?;
-            // if buy add amount_specified
-            let total_amount = if token_in == native_mint {
-                rent + amount_specified
+            // if buy add the max sol that can be spent
+            let lamports = if token_in == native_mint {
+                if swap_base_in {
+                    amount_specified
+                } else {
+                    other_amount_threshold
+                }
             } else {
-                rent
+                0
             };
             // create tmp wsol account
-            instructions.push(system_instruction::create_account_with_seed(
-                &owner,
-                &wsol_pubkey,
-                &owner,
-                seed,
-                total_amount,
-                Account::LEN as u64, // 165, // Token account size
-                &spl_token::id(),
-            ));
-
-            // initialize account
-            instructions.push(spl_token::instruction::initialize_account(
-                &spl_token::id(),
-                &wsol_pubkey,
-                &native_mint,
-                &owner,
-            )?);
+            let (wsol_pubkey, wsol_instructions) = self.wsol_account_instructions(lamports).await?;
+            wsol_account = Some(wsol_pubkey);
+            instructions.extend(wsol_instructions);
         }
 
         if let Some(create_instruction) = create_instruction {
@@ -258,7 +399,7 @@ impl Raydium {
                     &wsol_account,
                     &owner,
                     &owner,
-                    &vec![&owner],
+                    &[&owner],
                 )?);
             }
 
@@ -271,7 +412,6 @@ impl Raydium {
                 &final_out_ata,
                 amount_specified,
                 other_amount_threshold,
-                swap_base_in,
             )?;
             info!(
                 "amount_specified: {}, other_amount_threshold: {}, wsol_account: {:?}",
@@ -286,14 +426,509 @@ impl Raydium {
         if let Some(close_instruction) = close_instruction {
             instructions.push(close_instruction);
         }
-        if instructions.len() == 0 {
+        if instructions.is_empty() {
             return Err(anyhow!("instructions is empty, no tx required"));
         }
 
-        tx::new_signed_and_send(&self.client, &self.keypair, instructions, use_jito).await
+        tx::new_signed_and_send(&self.client, &self.keypair, instructions, options.send).await
+    }
+
+    pub async fn quote(
+        &self,
+        mint_str: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        slippage: Slippage,
+    ) -> Result<Quote> {
+        let mint = Pubkey::from_str(mint_str)
+            .map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let (amm_pool_id, pool_state) = self.find_pool(mint_str).await?;
+        let pair_mint = pair_mint(&pool_state, &mint);
+        if pair_mint != self.quote_mint {
+            return self
+                .quote_two_hop(
+                    mint,
+                    (amm_pool_id, pool_state),
+                    amount_in,
+                    swap_direction,
+                    in_type,
+                    slippage,
+                )
+                .await;
+        }
+        let (coin_reserve, pc_reserve) =
+            get_pool_reserves(self.client.clone(), &pool_state).await?;
+        debug!(
+            "amm pool: {}, coin_reserve: {}, pc_reserve: {}",
+            amm_pool_id, coin_reserve, pc_reserve
+        );
+        let input_mint = match swap_direction {
+            SwapDirection::Buy => self.quote_mint,
+            SwapDirection::Sell => mint,
+        };
+        let (reserve_in, reserve_out) =
+            directed_reserves(&pool_state, (coin_reserve, pc_reserve), &input_mint);
+        let fee = swap_fee(&pool_state);
+
+        let quote_decimals = self.mint_decimals(&self.quote_mint).await?;
+        let (amount_specified, decimals) = quote::resolve_pair_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            quote_decimals,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        let mut quote = Quote::new_pair(
+            Venue::RaydiumAmm,
+            &amm_pool_id,
+            &mint,
+            decimals,
+            &self.quote_mint,
+            quote_decimals,
+            &swap_direction,
+        );
+        let exact_out = matches!(in_type, SwapInType::ExactOut);
+        if exact_out {
+            let (amount_in, fee_amount) =
+                swap_base_out_amount(amount_specified, reserve_in, reserve_out, fee)?;
+            quote.amount_in = amount_in;
+            quote.amount_out = amount_specified;
+            quote.fee_amount = fee_amount;
+        } else {
+            let (amount_out, fee_amount) =
+                swap_base_in_amount(amount_specified, reserve_in, reserve_out, fee)?;
+            quote.amount_in = amount_specified;
+            quote.amount_out = amount_out;
+            quote.fee_amount = fee_amount;
+        }
+        // the swap fee is taken from the input token
+        quote.fee_mint = quote.input_mint.clone();
+        quote.reserve_in = reserve_in;
+        quote.reserve_out = reserve_out;
+        quote.price_impact_pct = quote::price_impact_pct(
+            quote.amount_in - quote.fee_amount,
+            quote.amount_out,
+            reserve_out as f64 / reserve_in as f64,
+        );
+        Ok(quote.with_slippage(slippage, exact_out))
+    }
+
+    /// Swaps the quote mint into the token through `via_mint` for buys and back for sells,
+    /// both legs in one transaction. The second leg spends the minimum output of the first,
+    /// the amount of a transaction has to be fixed when it is signed. Anything the first leg
+    /// receives above that stays in the via mint account and is returned as the leftover.
+    async fn swap_two_hop(
+        &self,
+        mint: Pubkey,
+        (token_pool_id, token_pool_state): (Pubkey, AmmInfo),
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        options: SwapOptions,
+    ) -> Result<SendResult> {
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out is not supported on two hop swaps"));
+        }
+        let slippage = options.slippage;
+        // the token pool pairs the mint with the stable mint the swap goes through
+        let via_mint = pair_mint(&token_pool_state, &mint);
+        let owner = self.keypair.pubkey();
+        let program_id = spl_token::ID;
+        let native_mint = spl_token::native_mint::ID;
+        let (via_pool_id, via_pool_state) =
+            get_pool_state_by_pair(self.client.clone(), &via_mint.to_string(), &self.quote_mint)
+                .await?;
+        let quote_decimals = self.mint_decimals(&self.quote_mint).await?;
+        let (amount_specified, _) = quote::resolve_pair_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            quote_decimals,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        if amount_specified == 0 {
+            return Err(anyhow!("instructions is empty, no tx required"));
+        }
+        if matches!(
+            (&swap_direction, &in_type),
+            (SwapDirection::Sell, SwapInType::Qty)
+        ) {
+            let token_account = token::get_account_info(
+                self.client.clone(),
+                self.keypair.clone(),
+                &mint,
+                &get_associated_token_address(&owner, &mint),
+            )
+            .await?;
+            amount::check_balance(amount_specified, token_account.base.amount)?;
+        }
+        let ((first_pool_id, first_pool_state), (second_pool_id, second_pool_state)) =
+            match swap_direction {
+                SwapDirection::Buy => (
+                    (via_pool_id, via_pool_state),
+                    (token_pool_id, token_pool_state),
+                ),
+                SwapDirection::Sell => (
+                    (token_pool_id, token_pool_state),
+                    (via_pool_id, via_pool_state),
+                ),
+            };
+        let (token_in, token_out) = match swap_direction {
+            SwapDirection::Buy => (self.quote_mint, mint),
+            SwapDirection::Sell => (mint, self.quote_mint),
+        };
+
+        let amm_program = Pubkey::from_str(AMM_PROGRAM)?;
+        let first_leg = calculate_swap_info(
+            &self.client,
+            amm_program,
+            first_pool_id,
+            input_vault(&first_pool_state, &token_in),
+            amount_specified,
+            slippage.bps(),
+            true,
+        )
+        .await?;
+        let via_amount = first_leg.other_amount_threshold;
+        let second_leg = calculate_swap_info(
+            &self.client,
+            amm_program,
+            second_pool_id,
+            input_vault(&second_pool_state, &via_mint),
+            via_amount,
+            slippage.bps(),
+            true,
+        )
+        .await?;
+        let other_amount_threshold = second_leg.other_amount_threshold;
+        info!(
+            "swap: {} -> {} -> {}, amount_specified: {}, via amount: {}, other_amount_threshold: {}",
+            token_in, via_mint, token_out, amount_specified, via_amount, other_amount_threshold
+        );
+
+        let mut instructions = vec![];
+        let mut in_account = get_associated_token_address(&owner, &token_in);
+        let mut out_account = get_associated_token_address(&owner, &token_out);
+        let via_account = get_associated_token_address(&owner, &via_mint);
+        let mut wsol_account = None;
+        if token_in == native_mint || token_out == native_mint {
+            let lamports = if token_in == native_mint {
+                amount_specified
+            } else {
+                0
+            };
+            let (wsol_pubkey, wsol_instructions) = self.wsol_account_instructions(lamports).await?;
+            instructions.extend(wsol_instructions);
+            if token_in == native_mint {
+                in_account = wsol_pubkey;
+            } else {
+                out_account = wsol_pubkey;
+            }
+            wsol_account = Some(wsol_pubkey);
+        }
+        instructions.push(create_associated_token_account_idempotent(
+            &owner,
+            &owner,
+            &via_mint,
+            &program_id,
+        ));
+        if token_out != native_mint {
+            instructions.push(create_associated_token_account_idempotent(
+                &owner,
+                &owner,
+                &token_out,
+                &program_id,
+            ));
+        }
+        instructions.push(amm_swap(
+            &amm_program,
+            first_leg,
+            &owner,
+            &in_account,
+            &via_account,
+            amount_specified,
+            via_amount,
+        )?);
+        instructions.push(amm_swap(
+            &amm_program,
+            second_leg,
+            &owner,
+            &via_account,
+            &out_account,
+            via_amount,
+            other_amount_threshold,
+        )?);
+        if let Some(wsol_account) = wsol_account {
+            instructions.push(spl_token::instruction::close_account(
+                &program_id,
+                &wsol_account,
+                &owner,
+                &owner,
+                &[&owner],
+            )?);
+        }
+        if matches!(swap_direction, SwapDirection::Sell)
+            && matches!(in_type, SwapInType::Pct)
+            && amount_in.is_all()
+        {
+            // sell all, close ata
+            info!("sell all. will be close ATA for mint {}", mint);
+            instructions.push(spl_token::instruction::close_account(
+                &program_id,
+                &in_account,
+                &owner,
+                &owner,
+                &[&owner],
+            )?);
+        }
+
+        let via_before = self.confirmed_token_balance(&via_account).await?;
+        let mut result =
+            tx::new_signed_and_send(&self.client, &self.keypair, instructions, options.send)
+                .await?;
+        if result.is_confirmed() {
+            // the swap is sent, a failed read only loses the report
+            match self.confirmed_token_balance(&via_account).await {
+                Ok(via_after) => {
+                    let amount = via_after.saturating_sub(via_before);
+                    if amount > 0 {
+                        info!("{} {} left in {}", amount, via_mint, via_account);
+                    }
+                    result.leftover = Some(Leftover {
+                        mint: via_mint.to_string(),
+                        amount,
+                    });
+                }
+                Err(err) => warn!("failed to read the leftover in {}: {}", via_account, err),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Balance of a token account at confirmed commitment, 0 when it does not exist.
+    async fn confirmed_token_balance(&self, account: &Pubkey) -> Result<u64> {
+        let account = self
+            .client
+            .get_account_with_commitment(account, CommitmentConfig::confirmed())
+            .await?
+            .value;
+        match account {
+            Some(account) => Ok(common_utils::unpack_token(&account.data)?.base.amount),
+            None => Ok(0),
+        }
+    }
+
+    async fn quote_two_hop(
+        &self,
+        mint: Pubkey,
+        (token_pool_id, token_pool_state): (Pubkey, AmmInfo),
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        slippage: Slippage,
+    ) -> Result<Quote> {
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out is not supported on two hop swaps"));
+        }
+        let via_mint = pair_mint(&token_pool_state, &mint);
+        let (via_pool_id, via_pool_state) =
+            get_pool_state_by_pair(self.client.clone(), &via_mint.to_string(), &self.quote_mint)
+                .await?;
+        let token_reserves = get_pool_reserves(self.client.clone(), &token_pool_state).await?;
+        let via_reserves = get_pool_reserves(self.client.clone(), &via_pool_state).await?;
+        // (via, token) and (quote, via)
+        let (token_pool_via, token_pool_token) =
+            directed_reserves(&token_pool_state, token_reserves, &via_mint);
+        let (via_pool_quote, via_pool_via) =
+            directed_reserves(&via_pool_state, via_reserves, &self.quote_mint);
+        let legs = match swap_direction {
+            SwapDirection::Buy => [
+                (via_pool_quote, via_pool_via, swap_fee(&via_pool_state)),
+                (
+                    token_pool_via,
+                    token_pool_token,
+                    swap_fee(&token_pool_state),
+                ),
+            ],
+            SwapDirection::Sell => [
+                (
+                    token_pool_token,
+                    token_pool_via,
+                    swap_fee(&token_pool_state),
+                ),
+                (via_pool_via, via_pool_quote, swap_fee(&via_pool_state)),
+            ],
+        };
+
+        let quote_decimals = self.mint_decimals(&self.quote_mint).await?;
+        let (amount_specified, decimals) = quote::resolve_pair_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            quote_decimals,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        let (via_amount, first_fee) =
+            swap_base_in_amount(amount_specified, legs[0].0, legs[0].1, legs[0].2)?;
+        let (amount_out, second_fee) =
+            swap_base_in_amount(via_amount, legs[1].0, legs[1].1, legs[1].2)?;
+
+        let mut quote = Quote::new_pair(
+            Venue::RaydiumAmm,
+            &token_pool_id,
+            &mint,
+            decimals,
+            &self.quote_mint,
+            quote_decimals,
+            &swap_direction,
+        );
+        quote.amount_in = amount_specified;
+        quote.amount_out = amount_out;
+        // the second fee is paid in the via mint, count it in the input mint at the first leg rate
+        quote.fee_amount = first_fee
+            + (second_fee as u128 * amount_specified as u128 / via_amount.max(1) as u128) as u64;
+        quote.fee_mint = quote.input_mint.clone();
+        let first_impact = quote::price_impact_pct(
+            amount_specified - first_fee,
+            via_amount,
+            legs[0].1 as f64 / legs[0].0 as f64,
+        );
+        let second_impact = quote::price_impact_pct(
+            via_amount - second_fee,
+            amount_out,
+            legs[1].1 as f64 / legs[1].0 as f64,
+        );
+        quote.price_impact_pct =
+            (1.0 - (1.0 - first_impact / 100.0) * (1.0 - second_impact / 100.0)) * 100.0;
+        // the via side of the token pool valued in the quote mint
+        let quote_per_via = via_pool_quote as f64 / via_pool_via.max(1) as f64;
+        let token_pool_quote = (token_pool_via as f64 * quote_per_via) as u64;
+        (quote.reserve_in, quote.reserve_out) = match swap_direction {
+            SwapDirection::Buy => (token_pool_quote, token_pool_token),
+            SwapDirection::Sell => (token_pool_token, token_pool_quote),
+        };
+        quote.via_mint = Some(via_mint.to_string());
+        quote.via_pool = Some(via_pool_id.to_string());
+
+        let mut quote = quote.with_slippage(slippage, false);
+        // each leg keeps its own slippage margin, the second one spends the first one's minimum
+        let (second_min, _) = swap_base_in_amount(
+            slippage.min_amount(via_amount),
+            legs[1].0,
+            legs[1].1,
+            legs[1].2,
+        )?;
+        quote.min_amount_out = slippage.min_amount(second_min);
+        Ok(quote)
+    }
+}
+
+/// The other mint of the pool.
+pub fn pair_mint(pool_state: &AmmInfo, mint: &Pubkey) -> Pubkey {
+    if pool_state.coin_vault_mint == *mint {
+        pool_state.pc_vault_mint
+    } else {
+        pool_state.coin_vault_mint
+    }
+}
+
+/// The pool vault receiving `input_mint`.
+fn input_vault(pool_state: &AmmInfo, input_mint: &Pubkey) -> Pubkey {
+    if pool_state.coin_vault_mint == *input_mint {
+        pool_state.coin_vault
+    } else {
+        pool_state.pc_vault
+    }
+}
+
+/// (coin, pc) reserves ordered as (input, output) for a swap spending `input_mint`.
+fn directed_reserves(
+    pool_state: &AmmInfo,
+    (coin_reserve, pc_reserve): (u64, u64),
+    input_mint: &Pubkey,
+) -> (u64, u64) {
+    if pool_state.coin_vault_mint == *input_mint {
+        (coin_reserve, pc_reserve)
+    } else {
+        (pc_reserve, coin_reserve)
+    }
+}
+
+fn swap_fee(pool_state: &AmmInfo) -> (u64, u64) {
+    (
+        pool_state.fees.swap_fee_numerator,
+        pool_state.fees.swap_fee_denominator,
+    )
+}
+
+/// Pool reserves (coin, pc) available for swaps, pnl owed to the protocol excluded.
+pub async fn get_pool_reserves(
+    rpc_client: Arc<RpcClient>,
+    pool_state: &AmmInfo,
+) -> Result<(u64, u64)> {
+    let load_pubkeys = vec![pool_state.coin_vault, pool_state.pc_vault];
+    let rsps = rpc_client.get_multiple_accounts(&load_pubkeys).await?;
+    let mut vault_amounts = vec![];
+    for rsp in rsps {
+        let account = rsp.ok_or(anyhow!("amm pool vault not found"))?;
+        vault_amounts.push(common_utils::unpack_token(&account.data)?.base.amount);
     }
+    let coin = vault_amounts[0].saturating_sub(pool_state.state_data.need_take_pnl_coin);
+    let pc = vault_amounts[1].saturating_sub(pool_state.state_data.need_take_pnl_pc);
+    Ok((coin, pc))
 }
 
+/// Output amount and swap fee for an exact input swap, the fee is taken from the input.
+pub fn swap_base_in_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    (fee_numerator, fee_denominator): (u64, u64),
+) -> Result<(u64, u64)> {
+    if reserve_in == 0 || reserve_out == 0 || fee_denominator == 0 {
+        return Err(anyhow!("invalid amm pool reserves"));
+    }
+    let fee = (amount_in as u128 * fee_numerator as u128).div_ceil(fee_denominator as u128) as u64;
+    let amount_in_less_fee = amount_in - fee.min(amount_in);
+    let amount_out = U128::from(reserve_out) * U128::from(amount_in_less_fee)
+        / (U128::from(reserve_in) + U128::from(amount_in_less_fee));
+    Ok((amount_out.as_u64(), fee))
+}
+
+/// Input amount and swap fee needed to receive exactly `amount_out`.
+pub fn swap_base_out_amount(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    (fee_numerator, fee_denominator): (u64, u64),
+) -> Result<(u64, u64)> {
+    if reserve_in == 0 || amount_out >= reserve_out || fee_numerator >= fee_denominator {
+        return Err(anyhow!(
+            "amount out {} exceeds the amm pool reserve {}",
+            amount_out,
+            reserve_out
+        ));
+    }
+    let amount_in_less_fee = (U128::from(reserve_in) * U128::from(amount_out))
+        .as_u128()
+        .div_ceil((reserve_out - amount_out) as u128);
+    let amount_in = (amount_in_less_fee * fee_denominator as u128)
+        .div_ceil((fee_denominator - fee_numerator) as u128) as u64;
+    Ok((amount_in, amount_in - amount_in_less_fee as u64))
+}
+
+/// Swap instruction through the pool of `result`, base in or base out as `result` was
+/// calculated.
 pub fn amm_swap(
     amm_program: &Pubkey,
     result: AmmSwapInfoResult,
@@ -302,11 +937,10 @@ pub fn amm_swap(
     user_destination: &Pubkey,
     amount_specified: u64,
     other_amount_threshold: u64,
-    swap_base_in: bool,
 ) -> Result<Instruction> {
-    let swap_instruction = if swap_base_in {
+    let swap_instruction = if result.swap_base_in {
         raydium_amm::instruction::swap_base_in(
-            &amm_program,
+            amm_program,
             &result.pool_id,
             &result.amm_authority,
             &result.amm_open_orders,
@@ -328,7 +962,7 @@ pub fn amm_swap(
         )?
     } else {
         raydium_amm::instruction::swap_base_out(
-            &amm_program,
+            amm_program,
             &result.pool_id,
             &result.amm_authority,
             &result.amm_open_orders,
@@ -353,69 +987,351 @@ pub fn amm_swap(
     Ok(swap_instruction)
 }
 
+/// The AMM v4 pool state at `pool_id`, none when the account does not exist.
+async fn get_amm_info(rpc_client: &RpcClient, pool_id: &Pubkey) -> Result<Option<AmmInfo>> {
+    let account = rpc_client
+        .get_account_with_commitment(pool_id, CommitmentConfig::processed())
+        .await?
+        .value;
+    match account {
+        Some(account) => Ok(Some(*AmmInfo::load_from_bytes(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// amm-cli only reads through the blocking client, so the swap info is calculated on a
+/// blocking thread with a blocking client sending through `rpc_client`.
+async fn calculate_swap_info(
+    rpc_client: &Arc<RpcClient>,
+    amm_program: Pubkey,
+    pool_id: Pubkey,
+    user_input_token: Pubkey,
+    amount_specified: u64,
+    slippage_bps: u64,
+    base_in: bool,
+) -> Result<AmmSwapInfoResult> {
+    let rpc_client = rpc_client.clone();
+    let handle = Handle::current();
+    tokio::task::spawn_blocking(move || {
+        let rpc_client = rpc::blocking_client(rpc_client, handle);
+        amm_cli::calculate_swap_info(
+            &rpc_client,
+            amm_program,
+            pool_id,
+            user_input_token,
+            amount_specified,
+            slippage_bps,
+            base_in,
+        )
+    })
+    .await?
+}
+
+/// Serum/OpenBook market state v3, up to the order book accounts.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SerumMarketState {
+    /// `serum` account padding
+    pub head: [u8; 5],
+    pub account_flags: u64,
+    pub own_address: [u8; 32],
+    pub vault_signer_nonce: u64,
+    pub coin_mint: [u8; 32],
+    pub pc_mint: [u8; 32],
+    pub coin_vault: [u8; 32],
+    pub coin_deposits_total: u64,
+    pub coin_fees_accrued: u64,
+    pub pc_vault: [u8; 32],
+    pub pc_deposits_total: u64,
+    pub pc_fees_accrued: u64,
+    pub pc_dust_threshold: u64,
+    pub req_q: [u8; 32],
+    pub event_q: [u8; 32],
+    pub bids: [u8; 32],
+    pub asks: [u8; 32],
+}
+
+impl SerumMarketState {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if !data.starts_with(b"serum") {
+            return Err(anyhow!("invalid serum market account"));
+        }
+        // only read the prefix we know about, the rest is lot sizes, fees and padding
+        Self::deserialize(&mut &data[..])
+            .map_err(|e| anyhow!("Failed to deserialize serum market account: {}", e))
+    }
+
+    /// Bids, asks, event queue, coin vault, pc vault and vault signer of the market at
+    /// `market`, owned by `market_program`.
+    pub fn swap_accounts(&self, market: &Pubkey, market_program: &Pubkey) -> Result<Vec<Pubkey>> {
+        if self.own_address != market.to_bytes() {
+            return Err(anyhow!("serum market account {} is another market", market));
+        }
+        let vault_signer = Pubkey::create_program_address(
+            &[market.as_ref(), &self.vault_signer_nonce.to_le_bytes()],
+            market_program,
+        )?;
+        Ok(vec![
+            Pubkey::new_from_array(self.bids),
+            Pubkey::new_from_array(self.asks),
+            Pubkey::new_from_array(self.event_q),
+            Pubkey::new_from_array(self.coin_vault),
+            Pubkey::new_from_array(self.pc_vault),
+            vault_signer,
+        ])
+    }
+}
+
+/// Serum/OpenBook market accounts a swap through the pool touches, see
+/// [`SerumMarketState::swap_accounts`].
+pub async fn get_market_accounts(
+    rpc_client: &RpcClient,
+    pool_state: &AmmInfo,
+) -> Result<Vec<Pubkey>> {
+    let data = rpc_client.get_account_data(&pool_state.market).await?;
+    SerumMarketState::decode(&data)?.swap_accounts(&pool_state.market, &pool_state.market_program)
+}
+
 pub async fn get_pool_state(
-    rpc_client: Arc<solana_client::rpc_client::RpcClient>,
+    rpc_client: Arc<RpcClient>,
     pool_id: Option<&str>,
     mint: Option<&str>,
 ) -> Result<(Pubkey, AmmInfo)> {
     if let Some(pool_id) = pool_id {
         debug!("finding pool state by pool_id: {}", pool_id);
         let amm_pool_id = Pubkey::from_str(pool_id)?;
-        let pool_state =
-            common::rpc::get_account::<raydium_amm::state::AmmInfo>(&rpc_client, &amm_pool_id)?
-                .ok_or(anyhow!("NotFoundPool: pool state not found"))?;
+        let pool_state = get_amm_info(&rpc_client, &amm_pool_id)
+            .await?
+            .ok_or_else(|| NotFoundPool("pool state not found".to_string()))?;
         Ok((amm_pool_id, pool_state))
     } else {
         if let Some(mint) = mint {
-            // find pool by mint via rpc
-            if let Ok(pool_state) = get_pool_state_by_mint(rpc_client.clone(), mint).await {
-                return Ok(pool_state);
-            }
+            // find pool by mint via rpc, only a pool the rpc does not know of is looked up
+            // in the raydium api
+            let err = match get_pool_state_by_mint(rpc_client.clone(), mint).await {
+                Ok(pool_state) => return Ok(pool_state),
+                Err(err) if NotFoundPool::is(&err) => err,
+                Err(err) => return Err(err),
+            };
             // find pool by mint via raydium api
             let pool_data = get_pool_info(&spl_token::native_mint::ID.to_string(), mint).await;
             if let Ok(pool_data) = pool_data {
                 let pool = pool_data
                     .get_pool()
-                    .ok_or(anyhow!("NotFoundPool: pool not found in raydium api"))?;
+                    .ok_or_else(|| NotFoundPool("pool not found in raydium api".to_string()))?;
+                // standard pools include cpmm pools, only amm v4 can be loaded as AmmInfo
+                if pool.program_id != AMM_PROGRAM {
+                    return Err(NotFoundPool(format!(
+                        "raydium api pool {} is not an amm v4 pool",
+                        pool.id
+                    ))
+                    .into());
+                }
                 let amm_pool_id = Pubkey::from_str(&pool.id)?;
                 debug!("finding pool state by raydium api: {}", amm_pool_id);
-                let pool_state = common::rpc::get_account::<raydium_amm::state::AmmInfo>(
-                    &rpc_client,
-                    &amm_pool_id,
-                )?
-                .ok_or(anyhow!("NotFoundPool: pool state not found"))?;
+                let pool_state = get_amm_info(&rpc_client, &amm_pool_id)
+                    .await?
+                    .ok_or_else(|| NotFoundPool("pool state not found".to_string()))?;
+                let open_time = pool_state.state_data.pool_open_time;
+                if !is_tradable(pool_state.status, open_time, unix_now()?) {
+                    return Err(NotFoundPool(format!(
+                        "no tradable pool, raydium api pool {} status {} open time {}",
+                        amm_pool_id, pool_state.status, open_time
+                    ))
+                    .into());
+                }
                 return Ok((amm_pool_id, pool_state));
             }
-            Err(anyhow!("NotFoundPool: pool state not found"))
+            Err(err)
         } else {
-            Err(anyhow!("NotFoundPool: pool state not found"))
+            Err(NotFoundPool("pool state not found".to_string()).into())
         }
     }
 }
 
+/// The deepest tradable AMM v4 pool of the mint, see [`get_ranked_pools_by_mint`].
 pub async fn get_pool_state_by_mint(
-    rpc_client: Arc<solana_client::rpc_client::RpcClient>,
+    rpc_client: Arc<RpcClient>,
     mint: &str,
 ) -> Result<(Pubkey, AmmInfo)> {
-    debug!("finding pool state by mint: {}", mint);
+    get_pool_state_by_pair(rpc_client, mint, &spl_token::native_mint::ID).await
+}
+
+/// The deepest tradable AMM v4 pool pairing the mint with `quote_mint`.
+pub async fn get_pool_state_by_pair(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+    quote_mint: &Pubkey,
+) -> Result<(Pubkey, AmmInfo)> {
+    let pool = get_ranked_pools(rpc_client, mint, quote_mint)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| NotFoundPool("pool state not found".to_string()))?;
+    if !pool.liquidity.tradable {
+        return Err(NotFoundPool(format!(
+            "no tradable pool, best pool {} status {} open time {}",
+            pool.pool_id, pool.liquidity.status, pool.liquidity.open_time
+        ))
+        .into());
+    }
+    Ok((pool.pool_id, pool.pool_state))
+}
+
+/// Status, open time and reserves of an AMM v4 pool, reserves are net of pnl owed to the pool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolLiquidity {
+    pub pool_id: String,
+    pub status: u64,
+    pub open_time: u64,
+    /// swaps are enabled and the pool is open
+    pub tradable: bool,
+    pub quote_mint: String,
+    /// raw units of the quote mint, lamports for SOL pools
+    pub quote_reserve: u64,
+    pub token_reserve: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RankedPool {
+    pub pool_id: Pubkey,
+    pub pool_state: AmmInfo,
+    pub liquidity: PoolLiquidity,
+}
+
+/// Whether the pool status allows swaps (Initialized, SwapOnly, WaitingTrade) and the pool is open.
+pub fn is_tradable(status: u64, open_time: u64, now: u64) -> bool {
+    matches!(status, 1 | 6 | 7) && open_time <= now
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+/// Tradable pools first, then by quote reserve, deepest first.
+pub fn cmp_liquidity(a: &PoolLiquidity, b: &PoolLiquidity) -> std::cmp::Ordering {
+    b.tradable
+        .cmp(&a.tradable)
+        .then(b.quote_reserve.cmp(&a.quote_reserve))
+        .then(a.open_time.cmp(&b.open_time))
+}
+
+/// Reads the vaults of all pools in batched requests and returns their liquidity.
+pub async fn get_pools_liquidity(
+    rpc_client: Arc<RpcClient>,
+    pools: &[(Pubkey, AmmInfo)],
+    quote_mint: &Pubkey,
+) -> Result<Vec<PoolLiquidity>> {
+    let vaults = pools
+        .iter()
+        .flat_map(|(_, pool_state)| [pool_state.coin_vault, pool_state.pc_vault])
+        .collect::<Vec<_>>();
+    let mut vault_amounts = Vec::with_capacity(vaults.len());
+    // getMultipleAccounts takes at most 100 accounts
+    for chunk in vaults.chunks(100) {
+        for rsp in rpc_client.get_multiple_accounts(chunk).await? {
+            let amount = match rsp {
+                Some(account) => common_utils::unpack_token(&account.data)?.base.amount,
+                None => 0,
+            };
+            vault_amounts.push(amount);
+        }
+    }
+    let now = unix_now()?;
+    let liquidity = pools
+        .iter()
+        .zip(vault_amounts.chunks(2))
+        .map(|((pool_id, pool_state), amounts)| {
+            let coin = amounts[0].saturating_sub(pool_state.state_data.need_take_pnl_coin);
+            let pc = amounts[1].saturating_sub(pool_state.state_data.need_take_pnl_pc);
+            let (quote_reserve, token_reserve) = if pool_state.coin_vault_mint == *quote_mint {
+                (coin, pc)
+            } else {
+                (pc, coin)
+            };
+            let open_time = pool_state.state_data.pool_open_time;
+            PoolLiquidity {
+                pool_id: pool_id.to_string(),
+                status: pool_state.status,
+                open_time,
+                tradable: is_tradable(pool_state.status, open_time, now),
+                quote_mint: quote_mint.to_string(),
+                quote_reserve,
+                token_reserve,
+            }
+        })
+        .collect();
+    Ok(liquidity)
+}
+
+/// All SOL paired AMM v4 pools of the mint, deepest tradable pool first.
+pub async fn get_ranked_pools_by_mint(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+) -> Result<Vec<RankedPool>> {
+    get_ranked_pools(rpc_client, mint, &spl_token::native_mint::ID).await
+}
+
+/// All AMM v4 pools pairing the mint with `quote_mint`, deepest tradable pool first.
+pub async fn get_ranked_pools(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+    quote_mint: &Pubkey,
+) -> Result<Vec<RankedPool>> {
+    let pools = get_pool_states_by_pair(rpc_client.clone(), mint, quote_mint).await?;
+    let liquidity = get_pools_liquidity(rpc_client, &pools, quote_mint).await?;
+    let mut ranked = pools
+        .into_iter()
+        .zip(liquidity)
+        .map(|((pool_id, pool_state), liquidity)| RankedPool {
+            pool_id,
+            pool_state,
+            liquidity,
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| cmp_liquidity(&a.liquidity, &b.liquidity));
+    debug!(
+        "ranked pools of {}: {:?}",
+        mint,
+        ranked
+            .iter()
+            .map(|pool| &pool.liquidity)
+            .collect::<Vec<_>>()
+    );
+    Ok(ranked)
+}
+
+/// All AMM v4 pools pairing the mint with SOL, in either order.
+pub async fn get_pool_states_by_mint(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+) -> Result<Vec<(Pubkey, AmmInfo)>> {
+    get_pool_states_by_pair(rpc_client, mint, &spl_token::native_mint::ID).await
+}
+
+/// All AMM v4 pools pairing the mint with `quote_mint`, in either order.
+pub async fn get_pool_states_by_pair(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+    quote_mint: &Pubkey,
+) -> Result<Vec<(Pubkey, AmmInfo)>> {
+    debug!(
+        "finding pool states by mint: {}, quote mint: {}",
+        mint, quote_mint
+    );
     // (pc_mint, coin_mint)
     let pairs = vec![
         // pump pool
-        (
-            Some(spl_token::native_mint::ID),
-            Pubkey::from_str(mint).ok(),
-        ),
+        (Some(*quote_mint), Pubkey::from_str(mint).ok()),
         // general pool
-        (
-            Pubkey::from_str(mint).ok(),
-            Some(spl_token::native_mint::ID),
-        ),
+        (Pubkey::from_str(mint).ok(), Some(*quote_mint)),
     ];
 
     let pool_len = core::mem::size_of::<raydium_amm::state::AmmInfo>() as u64;
     let amm_program = Pubkey::from_str(AMM_PROGRAM)?;
-    // Find matching AMM pool from mint pairs by filter
-    let mut found_pools = None;
+    // Find matching AMM pools from mint pairs by filter
+    let mut found_pools = Vec::new();
     for (coin_mint, pc_mint) in pairs {
         debug!(
             "get_pool_state_by_mint filter: coin_mint: {:?}, pc_mint: {:?}",
@@ -438,24 +1354,18 @@ pub async fn get_pool_state_by_mint(
             ]),
         };
         let pools =
-            common::rpc::get_program_accounts_with_filters(&rpc_client, amm_program, filters)
-                .unwrap();
-        if !pools.is_empty() {
-            found_pools = Some(pools);
-            break;
+            crate::rpc::get_program_accounts_with_filters(&rpc_client, amm_program, filters)
+                .await?;
+        for (pool_id, account) in pools {
+            let pool_state = raydium_amm::state::AmmInfo::load_from_bytes(&account.data)?;
+            found_pools.push((pool_id, *pool_state));
         }
     }
 
-    match found_pools {
-        Some(pools) => {
-            let pool = &pools[0];
-            let pool_state = raydium_amm::state::AmmInfo::load_from_bytes(&pools[0].1.data)?;
-            Ok((pool.0, pool_state.clone()))
-        }
-        None => {
-            return Err(anyhow!("NotFoundPool: pool state not found"));
-        }
+    if found_pools.is_empty() {
+        return Err(NotFoundPool("pool state not found".to_string()).into());
     }
+    Ok(found_pools)
 }
 
 // get pool info
@@ -545,3 +1455,119 @@ pub struct Mint {
     pub name: String,
     pub decimals: u8,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0.25% swap fee
+    const FEE: (u64, u64) = (25, 10000);
+
+    #[test]
+    fn test_serum_market_accounts() {
+        let market = Pubkey::new_unique();
+        let market_program = Pubkey::new_unique();
+        let (bids, asks, event_q) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (coin_vault, pc_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // the first nonce that derives an off curve address
+        let (vault_signer, nonce) = (0u64..)
+            .find_map(|nonce| {
+                Pubkey::create_program_address(
+                    &[market.as_ref(), &nonce.to_le_bytes()],
+                    &market_program,
+                )
+                .ok()
+                .map(|signer| (signer, nonce))
+            })
+            .unwrap();
+        let state = SerumMarketState {
+            head: *b"serum",
+            account_flags: 3,
+            own_address: market.to_bytes(),
+            vault_signer_nonce: nonce,
+            coin_mint: Pubkey::new_unique().to_bytes(),
+            pc_mint: spl_token::native_mint::ID.to_bytes(),
+            coin_vault: coin_vault.to_bytes(),
+            coin_deposits_total: 0,
+            coin_fees_accrued: 0,
+            pc_vault: pc_vault.to_bytes(),
+            pc_deposits_total: 0,
+            pc_fees_accrued: 0,
+            pc_dust_threshold: 0,
+            req_q: Pubkey::new_unique().to_bytes(),
+            event_q: event_q.to_bytes(),
+            bids: bids.to_bytes(),
+            asks: asks.to_bytes(),
+        };
+        let mut data = borsh::to_vec(&state).unwrap();
+        // the v3 layout, lot sizes and the rest follow the order book accounts
+        assert_eq!(data.len(), 349);
+        assert_eq!(&data[117..149], coin_vault.as_ref());
+        assert_eq!(&data[165..197], pc_vault.as_ref());
+        assert_eq!(&data[253..285], event_q.as_ref());
+        assert_eq!(&data[285..317], bids.as_ref());
+        assert_eq!(&data[317..349], asks.as_ref());
+        data.extend_from_slice(&[0; 39]);
+
+        let decoded = SerumMarketState::decode(&data).unwrap();
+        assert_eq!(
+            decoded.swap_accounts(&market, &market_program).unwrap(),
+            vec![bids, asks, event_q, coin_vault, pc_vault, vault_signer]
+        );
+        assert!(decoded
+            .swap_accounts(&Pubkey::new_unique(), &market_program)
+            .is_err());
+        data[..5].copy_from_slice(b"srm00");
+        assert!(SerumMarketState::decode(&data).is_err());
+    }
+
+    #[test]
+    fn test_swap_amounts() {
+        let (amount_out, fee) =
+            swap_base_in_amount(1_000_000_000, 100_000_000_000, 1_000_000_000_000, FEE).unwrap();
+        assert_eq!(fee, 2_500_000);
+        // 1e12 * 997_500_000 / (1e11 + 997_500_000)
+        assert_eq!(amount_out, 9_876_482_091);
+
+        // buying that exact output costs at most the original input
+        let (amount_in, fee) =
+            swap_base_out_amount(amount_out, 100_000_000_000, 1_000_000_000_000, FEE).unwrap();
+        assert!(amount_in <= 1_000_000_000);
+        assert!(amount_in > 999_999_000);
+        assert!(fee > 0);
+        assert!(
+            swap_base_out_amount(1_000_000_000_000, 100_000_000_000, 1_000_000_000_000, FEE)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_rank_pools() {
+        let pool = |quote_reserve: u64, status: u64, open_time: u64| PoolLiquidity {
+            pool_id: Pubkey::new_unique().to_string(),
+            status,
+            open_time,
+            tradable: is_tradable(status, open_time, 1_000),
+            quote_mint: spl_token::native_mint::ID.to_string(),
+            quote_reserve,
+            token_reserve: 1,
+        };
+        let dust = pool(1_000, 6, 0);
+        let deep = pool(100_000_000_000, 6, 0);
+        // deeper but disabled, or not open yet
+        let disabled = pool(500_000_000_000, 2, 0);
+        let waiting = pool(500_000_000_000, 7, 2_000);
+        assert!(!disabled.tradable);
+        assert!(!waiting.tradable);
+
+        let mut pools = [dust.clone(), disabled, waiting, deep.clone()];
+        pools.sort_by(cmp_liquidity);
+        assert_eq!(pools[0], deep);
+        assert_eq!(pools[1], dust);
+        assert!(!pools[2].tradable);
+    }
+}
@@ -0,0 +1,450 @@
+use anyhow::{anyhow, Result};
+
+pub use construct::U256;
+
+#[allow(clippy::manual_div_ceil, clippy::assign_op_pattern)]
+mod construct {
+    use uint::construct_uint;
+
+    construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+pub const MIN_TICK: i32 = -443636;
+pub const MAX_TICK: i32 = -MIN_TICK;
+pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
+pub const MAX_SQRT_PRICE_X64: u128 = 79226673521066979257578248091;
+pub const FEE_RATE_DENOMINATOR: u32 = 1_000_000;
+pub const TICK_ARRAY_SIZE: i32 = 60;
+const RESOLUTION: u32 = 64;
+
+// 2^64 / sqrt(1.0001)^(2^i)
+const TICK_RATIOS: [u128; 19] = [
+    0xfffcb933bd6fad37,
+    0xfff97272373d4132,
+    0xfff2e50f5f656932,
+    0xffe5caca7e10e4e6,
+    0xffcb9843d60f6159,
+    0xff973b41fa98c081,
+    0xff2ea16466c96a38,
+    0xfe5dee046a99a2a8,
+    0xfcbe86c7900a88ae,
+    0xf987a7253ac41317,
+    0xf3392b0822b70005,
+    0xe7159475a2c29b74,
+    0xd097f3bdfd2022b8,
+    0xa9f746462d870fdf,
+    0x70d869a156d2a1b8,
+    0x31be135f97d08fd9,
+    0x9aa508b5b7a84e1,
+    0x5d6af8dedb8119,
+    0x2216e584f5fa,
+];
+
+/// sqrt(1.0001^tick) as a Q64.64 number.
+pub fn get_sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return Err(anyhow!("clmm tick {} out of range", tick));
+    }
+    let mut ratio: u128 = 1 << RESOLUTION;
+    for (i, tick_ratio) in TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (1 << i) != 0 {
+            ratio = (ratio * tick_ratio) >> RESOLUTION;
+        }
+    }
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    Ok(ratio)
+}
+
+/// First tick of the tick array that contains `tick`.
+pub fn get_tick_array_start_index(tick: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    let mut start = tick / ticks_in_array;
+    if tick < 0 && tick % ticks_in_array != 0 {
+        start -= 1;
+    }
+    start * ticks_in_array
+}
+
+fn mul_div(a: U256, b: U256, denominator: U256, round_up: bool) -> Result<U256> {
+    if denominator.is_zero() {
+        return Err(anyhow!("clmm math division by zero"));
+    }
+    let product = a
+        .checked_mul(b)
+        .ok_or(anyhow!("clmm math multiplication overflow"))?;
+    let (quotient, remainder) = product.div_mod(denominator);
+    if round_up && !remainder.is_zero() {
+        Ok(quotient + 1)
+    } else {
+        Ok(quotient)
+    }
+}
+
+fn to_u64(value: U256) -> Result<u64> {
+    if value > U256::from(u64::MAX) {
+        return Err(anyhow!("clmm amount overflows u64"));
+    }
+    Ok(value.as_u64())
+}
+
+fn to_u128(value: U256) -> Result<u128> {
+    if value > U256::from(u128::MAX) {
+        return Err(anyhow!("clmm sqrt price overflows u128"));
+    }
+    Ok(value.as_u128())
+}
+
+/// Amount of token 0 between two sqrt prices: L * (sqrt_b - sqrt_a) / (sqrt_a * sqrt_b)
+pub fn get_delta_amount_0(
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u64> {
+    let (sqrt_a, sqrt_b) = if sqrt_price_a_x64 > sqrt_price_b_x64 {
+        (sqrt_price_b_x64, sqrt_price_a_x64)
+    } else {
+        (sqrt_price_a_x64, sqrt_price_b_x64)
+    };
+    if sqrt_a == 0 {
+        return Err(anyhow!("clmm sqrt price must be positive"));
+    }
+    let numerator_1 = U256::from(liquidity) << RESOLUTION;
+    let numerator_2 = U256::from(sqrt_b - sqrt_a);
+    let amount = mul_div(numerator_1, numerator_2, U256::from(sqrt_b), round_up)?;
+    let amount = mul_div(amount, U256::one(), U256::from(sqrt_a), round_up)?;
+    to_u64(amount)
+}
+
+/// Amount of token 1 between two sqrt prices: L * (sqrt_b - sqrt_a)
+pub fn get_delta_amount_1(
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u64> {
+    let (sqrt_a, sqrt_b) = if sqrt_price_a_x64 > sqrt_price_b_x64 {
+        (sqrt_price_b_x64, sqrt_price_a_x64)
+    } else {
+        (sqrt_price_a_x64, sqrt_price_b_x64)
+    };
+    let amount = mul_div(
+        U256::from(liquidity),
+        U256::from(sqrt_b - sqrt_a),
+        U256::one() << RESOLUTION,
+        round_up,
+    )?;
+    to_u64(amount)
+}
+
+/// Sqrt price after adding `amount_in` of the input token.
+pub fn get_next_sqrt_price_from_input(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Result<u128> {
+    if amount_in == 0 {
+        return Ok(sqrt_price_x64);
+    }
+    if liquidity == 0 {
+        return Err(anyhow!("clmm liquidity must be positive"));
+    }
+    if zero_for_one {
+        // L * sqrt_p / (L + amount * sqrt_p), rounded up
+        let numerator_1 = U256::from(liquidity) << RESOLUTION;
+        let product = U256::from(amount_in) * U256::from(sqrt_price_x64);
+        let next = mul_div(
+            numerator_1,
+            U256::from(sqrt_price_x64),
+            numerator_1 + product,
+            true,
+        )?;
+        to_u128(next)
+    } else {
+        // sqrt_p + amount / L, rounded down
+        let quotient = (U256::from(amount_in) << RESOLUTION) / U256::from(liquidity);
+        to_u128(U256::from(sqrt_price_x64) + quotient)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SwapStep {
+    pub sqrt_price_next_x64: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+/// Exact input swap within a single tick range.
+pub fn compute_swap_step(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_rate: u32,
+    zero_for_one: bool,
+) -> Result<SwapStep> {
+    let amount_remaining_less_fee = mul_div(
+        U256::from(amount_remaining),
+        U256::from(FEE_RATE_DENOMINATOR - fee_rate),
+        U256::from(FEE_RATE_DENOMINATOR),
+        false,
+    )?
+    .as_u64();
+    // an amount that overflows u64 means the target can't be reached with this input
+    let amount_to_target = if zero_for_one {
+        get_delta_amount_0(
+            sqrt_price_target_x64,
+            sqrt_price_current_x64,
+            liquidity,
+            true,
+        )
+    } else {
+        get_delta_amount_1(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            true,
+        )
+    }
+    .ok()
+    .filter(|amount| amount_remaining_less_fee >= *amount);
+    let sqrt_price_next_x64 = if amount_to_target.is_some() {
+        sqrt_price_target_x64
+    } else {
+        get_next_sqrt_price_from_input(
+            sqrt_price_current_x64,
+            liquidity,
+            amount_remaining_less_fee,
+            zero_for_one,
+        )?
+    };
+    let reached_target = sqrt_price_next_x64 == sqrt_price_target_x64;
+
+    let (amount_in, amount_out) = if zero_for_one {
+        (
+            match amount_to_target {
+                Some(amount) => amount,
+                None => get_delta_amount_0(
+                    sqrt_price_next_x64,
+                    sqrt_price_current_x64,
+                    liquidity,
+                    true,
+                )?,
+            },
+            get_delta_amount_1(
+                sqrt_price_next_x64,
+                sqrt_price_current_x64,
+                liquidity,
+                false,
+            )?,
+        )
+    } else {
+        (
+            match amount_to_target {
+                Some(amount) => amount,
+                None => get_delta_amount_1(
+                    sqrt_price_current_x64,
+                    sqrt_price_next_x64,
+                    liquidity,
+                    true,
+                )?,
+            },
+            get_delta_amount_0(
+                sqrt_price_current_x64,
+                sqrt_price_next_x64,
+                liquidity,
+                false,
+            )?,
+        )
+    };
+    let fee_amount = if !reached_target {
+        // the rest of the input is taken as fee
+        amount_remaining.saturating_sub(amount_in)
+    } else {
+        mul_div(
+            U256::from(amount_in),
+            U256::from(fee_rate),
+            U256::from(FEE_RATE_DENOMINATOR - fee_rate),
+            true,
+        )?
+        .as_u64()
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next_x64,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// An initialized tick and the liquidity change when crossing it left to right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InitializedTick {
+    pub tick: i32,
+    pub liquidity_net: i128,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SwapResult {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    pub sqrt_price_x64: u128,
+    pub crossed_ticks: u32,
+}
+
+/// Simulates an exact input swap across the initialized ticks that were loaded.
+/// `ticks` must be sorted ascending and cover the whole range the swap moves through.
+pub fn swap_base_input(
+    sqrt_price_x64: u128,
+    tick_current: i32,
+    liquidity: u128,
+    ticks: &[InitializedTick],
+    fee_rate: u32,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Result<SwapResult> {
+    let mut result = SwapResult {
+        sqrt_price_x64,
+        ..Default::default()
+    };
+    let mut tick_current = tick_current;
+    let mut liquidity = liquidity;
+    let mut amount_remaining = amount_in;
+
+    while amount_remaining > 0 {
+        let next_tick = if zero_for_one {
+            ticks.iter().rev().find(|t| t.tick <= tick_current)
+        } else {
+            ticks.iter().find(|t| t.tick > tick_current)
+        }
+        .ok_or(anyhow!(
+            "clmm swap exceeds the loaded tick arrays, not enough liquidity"
+        ))?;
+        let sqrt_price_target_x64 =
+            get_sqrt_price_at_tick(next_tick.tick.clamp(MIN_TICK, MAX_TICK))?;
+
+        let step = compute_swap_step(
+            result.sqrt_price_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            amount_remaining,
+            fee_rate,
+            zero_for_one,
+        )?;
+        amount_remaining = amount_remaining
+            .checked_sub(step.amount_in + step.fee_amount)
+            .ok_or(anyhow!("clmm swap step exceeds amount remaining"))?;
+        result.amount_in += step.amount_in + step.fee_amount;
+        result.amount_out += step.amount_out;
+        result.fee_amount += step.fee_amount;
+        result.sqrt_price_x64 = step.sqrt_price_next_x64;
+
+        if step.sqrt_price_next_x64 != sqrt_price_target_x64 {
+            // the input ran out inside this tick range
+            break;
+        }
+        // cross the tick
+        let liquidity_net = if zero_for_one {
+            -next_tick.liquidity_net
+        } else {
+            next_tick.liquidity_net
+        };
+        liquidity = liquidity
+            .checked_add_signed(liquidity_net)
+            .ok_or(anyhow!("clmm liquidity overflow when crossing tick"))?;
+        tick_current = if zero_for_one {
+            next_tick.tick - 1
+        } else {
+            next_tick.tick
+        };
+        result.crossed_ticks += 1;
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q64: u128 = 1 << 64;
+
+    #[test]
+    fn test_get_sqrt_price_at_tick() {
+        assert_eq!(get_sqrt_price_at_tick(0).unwrap(), Q64);
+        // sqrt(1.0001) * 2^64 = 18447666387855959850.9
+        let up = get_sqrt_price_at_tick(1).unwrap();
+        assert!(up.abs_diff(18447666387855959850) <= 1);
+        // every step is within one ulp of 1.0001^(tick/2)
+        for tick in [-100_000, -1000, -1, 1, 1000, 100_000] {
+            let expected = 1.0001f64.powf(tick as f64 / 2.0) * Q64 as f64;
+            let actual = get_sqrt_price_at_tick(tick).unwrap() as f64;
+            assert!((actual / expected - 1.0).abs() < 1e-12, "tick {}", tick);
+        }
+        assert!(get_sqrt_price_at_tick(MIN_TICK).unwrap() >= MIN_SQRT_PRICE_X64);
+        assert!(get_sqrt_price_at_tick(MAX_TICK).unwrap() <= MAX_SQRT_PRICE_X64);
+        assert!(get_sqrt_price_at_tick(MAX_TICK + 1).is_err());
+    }
+
+    #[test]
+    fn test_get_tick_array_start_index() {
+        assert_eq!(get_tick_array_start_index(0, 10), 0);
+        assert_eq!(get_tick_array_start_index(599, 10), 0);
+        assert_eq!(get_tick_array_start_index(600, 10), 600);
+        assert_eq!(get_tick_array_start_index(-1, 10), -600);
+        assert_eq!(get_tick_array_start_index(-600, 10), -600);
+        assert_eq!(get_tick_array_start_index(-601, 10), -1200);
+    }
+
+    #[test]
+    fn test_swap_within_single_range() {
+        // price 1.0, liquidity large enough that no tick is crossed
+        let liquidity = 1_000_000_000_000u128;
+        let ticks = [
+            InitializedTick {
+                tick: -600,
+                liquidity_net: liquidity as i128,
+            },
+            InitializedTick {
+                tick: 600,
+                liquidity_net: -(liquidity as i128),
+            },
+        ];
+        let result = swap_base_input(Q64, 0, liquidity, &ticks, 2500, 1_000_000, true).unwrap();
+        assert_eq!(result.amount_in, 1_000_000);
+        assert_eq!(result.crossed_ticks, 0);
+        // 0.25% fee and a tiny price impact
+        assert!(result.amount_out < 997_500 && result.amount_out > 997_400);
+        assert!(result.sqrt_price_x64 < Q64);
+
+        let result = swap_base_input(Q64, 0, liquidity, &ticks, 2500, 1_000_000, false).unwrap();
+        assert!(result.amount_out < 997_500 && result.amount_out > 997_400);
+        assert!(result.sqrt_price_x64 > Q64);
+    }
+
+    #[test]
+    fn test_swap_runs_out_of_ticks() {
+        let liquidity = 1_000_000u128;
+        let ticks = [
+            InitializedTick {
+                tick: -10,
+                liquidity_net: liquidity as i128,
+            },
+            InitializedTick {
+                tick: 10,
+                liquidity_net: -(liquidity as i128),
+            },
+        ];
+        // crossing tick 10 drains all liquidity, there is nothing left to the right
+        assert!(swap_base_input(Q64, 0, liquidity, &ticks, 2500, u32::MAX as u64, false).is_err());
+    }
+}
@@ -0,0 +1,715 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use borsh::BorshDeserialize;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use common::common_utils;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+use spl_token::{amount_to_ui_amount, state::Account};
+use spl_token_client::token::TokenError;
+use tracing::{debug, error, info};
+
+use crate::{
+    amount::{self, Amount},
+    quote::{self, Quote},
+    slippage::Slippage,
+    swap::{NotFoundPool, SwapDirection, SwapInType, SwapOptions, Venue},
+    token,
+    tx::{self, SendResult},
+};
+
+pub mod math;
+
+use math::{get_tick_array_start_index, InitializedTick, TICK_ARRAY_SIZE};
+
+pub const CLMM_PROGRAM: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+pub const MEMO_PROGRAM: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+pub const CLMM_SWAP_V2_METHOD: u64 = 7070309578724672555;
+pub const CLMM_POOL_STATE_DISCRIMINATOR: u64 = 5106734359795461623;
+pub const CLMM_AMM_CONFIG_DISCRIMINATOR: u64 = 8010720436694414554;
+pub const CLMM_TICK_ARRAY_DISCRIMINATOR: u64 = 3063003213882301376;
+pub const CLMM_POOL_STATE_LEN: u64 = 1544;
+// discriminator(8) + bump(1) + amm_config(32) + owner(32)
+const POOL_TOKEN_MINT_0_OFFSET: usize = 73;
+const POOL_TOKEN_MINT_1_OFFSET: usize = 105;
+// how many tick arrays to look ahead of the current one when quoting
+const TICK_ARRAY_LOOKAHEAD: i32 = 10;
+// how many initialized tick arrays are passed to the swap instruction
+const MAX_SWAP_TICK_ARRAYS: usize = 3;
+
+pub struct RaydiumClmm {
+    pub client: Arc<RpcClient>,
+    pub keypair: Arc<Keypair>,
+    pub pool_id: Option<String>,
+}
+
+impl RaydiumClmm {
+    pub fn new(client: Arc<RpcClient>, keypair: Arc<Keypair>) -> Self {
+        Self {
+            client,
+            keypair,
+            pool_id: None,
+        }
+    }
+
+    pub fn with_pool_id(&mut self, pool_id: Option<String>) -> &mut Self {
+        self.pool_id = pool_id;
+        self
+    }
+
+    pub async fn swap(
+        &self,
+        mint_str: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        options: SwapOptions,
+    ) -> Result<SendResult> {
+        let slippage = options.slippage;
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out swap is not supported in raydium clmm"));
+        }
+        let owner = self.keypair.pubkey();
+        let mint = Pubkey::from_str(mint_str)
+            .map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let native_mint = spl_token::native_mint::ID;
+        let clmm_program = Pubkey::from_str(CLMM_PROGRAM)?;
+
+        let (pool_id, pool_state) =
+            get_pool_state(self.client.clone(), self.pool_id.as_deref(), Some(mint_str)).await?;
+        let amm_config = get_amm_config(
+            self.client.clone(),
+            &Pubkey::new_from_array(pool_state.amm_config),
+        )
+        .await?;
+        debug!(
+            "clmm pool: {}, liquidity: {}, tick_current: {}",
+            pool_id, pool_state.liquidity, pool_state.tick_current
+        );
+
+        let sol_is_token_0 = pool_state.token_mint_0 == native_mint.to_bytes();
+        let (token_in, token_out) = match swap_direction {
+            SwapDirection::Buy => (native_mint, mint),
+            SwapDirection::Sell => (mint, native_mint),
+        };
+        // zero for one: token 0 in, token 1 out, the price moves down
+        let zero_for_one = matches!(
+            (swap_direction.clone(), sol_is_token_0),
+            (SwapDirection::Buy, true) | (SwapDirection::Sell, false)
+        );
+
+        let mint_program = token::get_mint_program(self.client.clone(), &mint).await?;
+        let mint_ata = get_associated_token_address_with_program_id(&owner, &mint, &mint_program);
+
+        let mut create_instruction = None;
+        let mut close_instruction = None;
+
+        let (amount_specified, amount_ui_pretty) = match swap_direction {
+            SwapDirection::Buy => {
+                // Create base ATA if it doesn't exist.
+                match token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &token_out,
+                    &mint_ata,
+                )
+                .await
+                {
+                    Ok(_) => debug!("base ata exists. skipping creation.."),
+                    Err(TokenError::AccountNotFound) | Err(TokenError::AccountInvalidOwner) => {
+                        info!(
+                            "base ATA for mint {} does not exist. will be create",
+                            token_out
+                        );
+                        create_instruction = Some(create_associated_token_account(
+                            &owner,
+                            &owner,
+                            &token_out,
+                            &mint_program,
+                        ));
+                    }
+                    Err(error) => error!("error retrieving out ATA: {}", error),
+                }
+
+                if matches!(in_type, SwapInType::Usd | SwapInType::Pct) {
+                    return Err(quote::unresolved_buy(&in_type));
+                }
+                let amount = amount_in.to_raw(spl_token::native_mint::DECIMALS)?;
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, spl_token::native_mint::DECIMALS),
+                        spl_token::native_mint::DECIMALS,
+                    ),
+                )
+            }
+            SwapDirection::Sell => {
+                let in_account = token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &token_in,
+                    &mint_ata,
+                )
+                .await?;
+                let in_mint =
+                    token::get_mint_info(self.client.clone(), self.keypair.clone(), &token_in)
+                        .await?;
+                let amount = match in_type {
+                    SwapInType::Qty => {
+                        let amount = amount_in.to_raw(in_mint.base.decimals)?;
+                        amount::check_balance(amount, in_account.base.amount)?;
+                        amount
+                    }
+                    SwapInType::ExactOut => {
+                        return Err(anyhow!("exact out swap is not supported in raydium clmm"))
+                    }
+                    SwapInType::Usd => return Err(anyhow!("usd amount only support buy")),
+                    SwapInType::Pct => {
+                        if amount_in.is_all() {
+                            // sell all, close ata
+                            info!("sell all. will be close ATA for mint {}", token_in);
+                            close_instruction = Some(spl_token_2022::instruction::close_account(
+                                &mint_program,
+                                &mint_ata,
+                                &owner,
+                                &owner,
+                                &[&owner],
+                            )?);
+                            in_account.base.amount
+                        } else {
+                            amount_in.share_of(in_account.base.amount)?
+                        }
+                    }
+                };
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, in_mint.base.decimals),
+                        in_mint.base.decimals,
+                    ),
+                )
+            }
+        };
+
+        let tick_arrays =
+            get_swap_tick_arrays(self.client.clone(), &pool_id, &pool_state, zero_for_one).await?;
+        // Token-2022 transfer fees are withheld from what the pool receives on a sell and
+        // from what the owner receives on a buy, the min out is checked after the fee
+        let transfer_fee =
+            token::get_transfer_fee(self.client.clone(), self.keypair.clone(), &mint).await?;
+        let mut swap_result = math::swap_base_input(
+            pool_state.sqrt_price_x64,
+            pool_state.tick_current,
+            pool_state.liquidity,
+            &tick_arrays.initialized_ticks(),
+            amm_config.trade_fee_rate,
+            match swap_direction {
+                SwapDirection::Buy => amount_specified,
+                SwapDirection::Sell => transfer_fee.post_fee_amount(amount_specified),
+            },
+            zero_for_one,
+        )?;
+        if let SwapDirection::Buy = swap_direction {
+            swap_result.amount_out = transfer_fee.post_fee_amount(swap_result.amount_out);
+        }
+        let minimum_amount_out = slippage.min_amount(swap_result.amount_out);
+
+        info!(
+            "swap: {}, value: {:?} -> {}",
+            token_in, amount_ui_pretty, token_out
+        );
+        info!(
+            "amount_specified: {}, amount_out: {}, minimum_amount_out: {}, crossed_ticks: {}",
+            amount_specified, swap_result.amount_out, minimum_amount_out, swap_result.crossed_ticks
+        );
+
+        // build instructions
+        let mut instructions = vec![];
+        if let Some(create_instruction) = create_instruction {
+            instructions.push(create_instruction);
+        }
+        if amount_specified > 0 {
+            // sol <-> wsol support
+            let seed = &format!("{}", Keypair::new().pubkey())[..32];
+            let wsol_account = Pubkey::create_with_seed(&owner, seed, &spl_token::id())?;
+            let rent = self
+                .client
+                .get_minimum_balance_for_rent_exemption(Account::LEN)
+                .await?;
+            // if buy add amount_specified
+            let total_amount = match swap_direction {
+                SwapDirection::Buy => rent + amount_specified,
+                SwapDirection::Sell => rent,
+            };
+            instructions.push(system_instruction::create_account_with_seed(
+                &owner,
+                &wsol_account,
+                &owner,
+                seed,
+                total_amount,
+                Account::LEN as u64,
+                &spl_token::id(),
+            ));
+            instructions.push(spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &wsol_account,
+                &native_mint,
+                &owner,
+            )?);
+
+            let (user_input, user_output) = match swap_direction {
+                SwapDirection::Buy => (wsol_account, mint_ata),
+                SwapDirection::Sell => (mint_ata, wsol_account),
+            };
+            instructions.push(swap_v2_instruction(
+                &clmm_program,
+                &pool_id,
+                &pool_state,
+                &owner,
+                &user_input,
+                &user_output,
+                zero_for_one,
+                &tick_arrays,
+                amount_specified,
+                minimum_amount_out,
+            ));
+            // close wsol account
+            instructions.push(spl_token::instruction::close_account(
+                &spl_token::id(),
+                &wsol_account,
+                &owner,
+                &owner,
+                &[&owner],
+            )?);
+        }
+        if let Some(close_instruction) = close_instruction {
+            instructions.push(close_instruction);
+        }
+        if instructions.is_empty() {
+            return Err(anyhow!("instructions is empty, no tx required"));
+        }
+
+        tx::new_signed_and_send(&self.client, &self.keypair, instructions, options.send).await
+    }
+
+    pub async fn quote(
+        &self,
+        mint_str: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        slippage: Slippage,
+    ) -> Result<Quote> {
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out swap is not supported in raydium clmm"));
+        }
+        let mint = Pubkey::from_str(mint_str)
+            .map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let native_mint = spl_token::native_mint::ID;
+
+        let (pool_id, pool_state) =
+            get_pool_state(self.client.clone(), self.pool_id.as_deref(), Some(mint_str)).await?;
+        let amm_config = get_amm_config(
+            self.client.clone(),
+            &Pubkey::new_from_array(pool_state.amm_config),
+        )
+        .await?;
+        let sol_is_token_0 = pool_state.token_mint_0 == native_mint.to_bytes();
+        let zero_for_one = matches!(
+            (swap_direction.clone(), sol_is_token_0),
+            (SwapDirection::Buy, true) | (SwapDirection::Sell, false)
+        );
+
+        let (amount_specified, decimals) = quote::resolve_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        let tick_arrays =
+            get_swap_tick_arrays(self.client.clone(), &pool_id, &pool_state, zero_for_one).await?;
+        let swap_result = math::swap_base_input(
+            pool_state.sqrt_price_x64,
+            pool_state.tick_current,
+            pool_state.liquidity,
+            &tick_arrays.initialized_ticks(),
+            amm_config.trade_fee_rate,
+            amount_specified,
+            zero_for_one,
+        )?;
+
+        let mut quote = Quote::new(
+            Venue::RaydiumClmm,
+            &pool_id,
+            &mint,
+            decimals,
+            &swap_direction,
+        );
+        quote.amount_in = amount_specified;
+        quote.amount_out = swap_result.amount_out;
+        // the trade fee is taken from the input token
+        quote.fee_amount = swap_result.fee_amount;
+        quote.fee_mint = quote.input_mint.clone();
+        // token 1 per token 0 at the current sqrt price
+        let sqrt_price = pool_state.sqrt_price_x64 as f64 / (1u128 << 64) as f64;
+        let price = sqrt_price.powi(2);
+        // guards look at what the pool actually holds, not the active range virtual amounts
+        let (reserve_0, reserve_1) = get_pool_reserves(self.client.clone(), &pool_state).await?;
+        (quote.reserve_in, quote.reserve_out) = if zero_for_one {
+            (reserve_0, reserve_1)
+        } else {
+            (reserve_1, reserve_0)
+        };
+        quote.price_impact_pct = quote::price_impact_pct(
+            swap_result.amount_in - swap_result.fee_amount,
+            swap_result.amount_out,
+            if zero_for_one { price } else { 1.0 / price },
+        );
+        Ok(quote.with_slippage(slippage, false))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap_v2_instruction(
+    clmm_program: &Pubkey,
+    pool_id: &Pubkey,
+    pool_state: &ClmmPoolState,
+    payer: &Pubkey,
+    user_input: &Pubkey,
+    user_output: &Pubkey,
+    zero_for_one: bool,
+    tick_arrays: &SwapTickArrays,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    let token_0 = (pool_state.token_vault_0, pool_state.token_mint_0);
+    let token_1 = (pool_state.token_vault_1, pool_state.token_mint_1);
+    let (input, output) = if zero_for_one {
+        (token_0, token_1)
+    } else {
+        (token_1, token_0)
+    };
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pool_state.amm_config), false),
+        AccountMeta::new(*pool_id, false),
+        AccountMeta::new(*user_input, false),
+        AccountMeta::new(*user_output, false),
+        AccountMeta::new(Pubkey::new_from_array(input.0), false),
+        AccountMeta::new(Pubkey::new_from_array(output.0), false),
+        AccountMeta::new(Pubkey::new_from_array(pool_state.observation_key), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(Pubkey::from_str(MEMO_PROGRAM).unwrap(), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(input.1), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(output.1), false),
+    ];
+    // remaining accounts: bitmap extension (if any) followed by tick arrays in swap order
+    if let Some(bitmap_extension) = tick_arrays.bitmap_extension {
+        accounts.push(AccountMeta::new_readonly(bitmap_extension, false));
+    }
+    for (tick_array, _) in tick_arrays.tick_arrays.iter().take(MAX_SWAP_TICK_ARRAYS) {
+        accounts.push(AccountMeta::new(*tick_array, false));
+    }
+    // sqrt_price_limit_x64 = 0 means no limit, is_base_input = true
+    Instruction::new_with_bincode(
+        *clmm_program,
+        &(
+            CLMM_SWAP_V2_METHOD,
+            amount_in,
+            minimum_amount_out,
+            0u128,
+            true,
+        ),
+        accounts,
+    )
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct ClmmPoolState {
+    pub discriminator: u64,
+    pub bump: u8,
+    pub amm_config: [u8; 32],
+    pub owner: [u8; 32],
+    pub token_mint_0: [u8; 32],
+    pub token_mint_1: [u8; 32],
+    pub token_vault_0: [u8; 32],
+    pub token_vault_1: [u8; 32],
+    pub observation_key: [u8; 32],
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub padding3: u16,
+    pub padding4: u16,
+    pub fee_growth_global_0_x64: u128,
+    pub fee_growth_global_1_x64: u128,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub swap_in_amount_token_0: u128,
+    pub swap_out_amount_token_1: u128,
+    pub swap_in_amount_token_1: u128,
+    pub swap_out_amount_token_0: u128,
+    pub status: u8,
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct ClmmAmmConfig {
+    pub discriminator: u64,
+    pub bump: u8,
+    pub index: u16,
+    pub owner: [u8; 32],
+    pub protocol_fee_rate: u32,
+    pub trade_fee_rate: u32,
+    pub tick_spacing: u16,
+    pub fund_fee_rate: u32,
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct TickState {
+    pub tick: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+    pub fee_growth_outside_0_x64: u128,
+    pub fee_growth_outside_1_x64: u128,
+    pub reward_growths_outside_x64: [u128; 3],
+    pub padding: [u32; 13],
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct TickArrayState {
+    pub discriminator: u64,
+    pub pool_id: [u8; 32],
+    pub start_tick_index: i32,
+    pub ticks: [TickState; TICK_ARRAY_SIZE as usize],
+    pub initialized_tick_count: u8,
+}
+
+/// Accounts and state needed to quote and execute a swap across tick arrays.
+#[derive(Debug, Clone, Default)]
+pub struct SwapTickArrays {
+    pub bitmap_extension: Option<Pubkey>,
+    /// Initialized tick arrays in the swap direction, starting at the current one.
+    pub tick_arrays: Vec<(Pubkey, TickArrayState)>,
+}
+
+impl SwapTickArrays {
+    /// Initialized ticks of the tick arrays the swap instruction can reach, sorted ascending.
+    pub fn initialized_ticks(&self) -> Vec<InitializedTick> {
+        let mut ticks = self
+            .tick_arrays
+            .iter()
+            .take(MAX_SWAP_TICK_ARRAYS)
+            .flat_map(|(_, tick_array)| tick_array.ticks.iter())
+            .filter(|tick| tick.liquidity_gross != 0)
+            .map(|tick| InitializedTick {
+                tick: tick.tick,
+                liquidity_net: tick.liquidity_net,
+            })
+            .collect::<Vec<_>>();
+        ticks.sort_by_key(|tick| tick.tick);
+        ticks
+    }
+}
+
+fn decode_account<T: BorshDeserialize>(data: &[u8], discriminator: u64, name: &str) -> Result<T> {
+    if data.len() < 8 || u64::from_le_bytes(data[..8].try_into()?) != discriminator {
+        return Err(anyhow!("invalid {} account discriminator", name));
+    }
+    // only read the prefix we know about, the rest is padding
+    T::deserialize(&mut &data[..])
+        .map_err(|e| anyhow!("Failed to deserialize {} account: {}", name, e))
+}
+
+pub async fn get_pool_state(
+    rpc_client: Arc<RpcClient>,
+    pool_id: Option<&str>,
+    mint: Option<&str>,
+) -> Result<(Pubkey, ClmmPoolState)> {
+    if let Some(pool_id) = pool_id {
+        debug!("finding clmm pool state by pool_id: {}", pool_id);
+        let pool_id = Pubkey::from_str(pool_id)?;
+        let pool_data = rpc_client.get_account_data(&pool_id).await?;
+        let pool_state =
+            decode_account(&pool_data, CLMM_POOL_STATE_DISCRIMINATOR, "clmm pool state")?;
+        Ok((pool_id, pool_state))
+    } else if let Some(mint) = mint {
+        get_pool_state_by_mint(rpc_client, mint).await
+    } else {
+        Err(NotFoundPool("clmm pool state not found".to_string()).into())
+    }
+}
+
+/// Finds the SOL paired clmm pool of a mint, the one with the most active liquidity
+/// wins when there are several fee tiers.
+pub async fn get_pool_state_by_mint(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+) -> Result<(Pubkey, ClmmPoolState)> {
+    debug!("finding clmm pool state by mint: {}", mint);
+    let mint = Pubkey::from_str(mint)?;
+    let native_mint = spl_token::native_mint::ID;
+    let clmm_program = Pubkey::from_str(CLMM_PROGRAM)?;
+    // (token_mint_0, token_mint_1)
+    let pairs = vec![(mint, native_mint), (native_mint, mint)];
+    let mut found_pools = vec![];
+    for (token_mint_0, token_mint_1) in pairs {
+        let filters = Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                POOL_TOKEN_MINT_0_OFFSET,
+                &token_mint_0.to_bytes(),
+            )),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                POOL_TOKEN_MINT_1_OFFSET,
+                &token_mint_1.to_bytes(),
+            )),
+            RpcFilterType::DataSize(CLMM_POOL_STATE_LEN),
+        ]);
+        let pools =
+            crate::rpc::get_program_accounts_with_filters(&rpc_client, clmm_program, filters)
+                .await?;
+        for (pool_id, account) in pools {
+            let pool_state: ClmmPoolState = decode_account(
+                &account.data,
+                CLMM_POOL_STATE_DISCRIMINATOR,
+                "clmm pool state",
+            )?;
+            found_pools.push((pool_id, pool_state));
+        }
+    }
+    Ok(found_pools
+        .into_iter()
+        .max_by_key(|(_, pool_state)| pool_state.liquidity)
+        .ok_or_else(|| NotFoundPool("clmm pool state not found".to_string()))?)
+}
+
+/// Returns the (token_0, token_1) vault balances minus protocol fees owed.
+pub async fn get_pool_reserves(
+    rpc_client: Arc<RpcClient>,
+    pool_state: &ClmmPoolState,
+) -> Result<(u64, u64)> {
+    let load_pubkeys = vec![
+        Pubkey::new_from_array(pool_state.token_vault_0),
+        Pubkey::new_from_array(pool_state.token_vault_1),
+    ];
+    let rsps = rpc_client.get_multiple_accounts(&load_pubkeys).await?;
+    let mut vault_amounts = vec![];
+    for rsp in rsps {
+        let account = rsp.ok_or(anyhow!("clmm pool vault not found"))?;
+        vault_amounts.push(common_utils::unpack_token(&account.data)?.base.amount);
+    }
+    Ok((
+        vault_amounts[0].saturating_sub(pool_state.protocol_fees_token_0),
+        vault_amounts[1].saturating_sub(pool_state.protocol_fees_token_1),
+    ))
+}
+
+pub async fn get_amm_config(
+    rpc_client: Arc<RpcClient>,
+    amm_config: &Pubkey,
+) -> Result<ClmmAmmConfig> {
+    let amm_config_data = rpc_client.get_account_data(amm_config).await?;
+    decode_account(
+        &amm_config_data,
+        CLMM_AMM_CONFIG_DISCRIMINATOR,
+        "clmm amm config",
+    )
+}
+
+/// Loads the bitmap extension and the initialized tick arrays a swap will walk through.
+pub async fn get_swap_tick_arrays(
+    rpc_client: Arc<RpcClient>,
+    pool_id: &Pubkey,
+    pool_state: &ClmmPoolState,
+    zero_for_one: bool,
+) -> Result<SwapTickArrays> {
+    let clmm_program = Pubkey::from_str(CLMM_PROGRAM)?;
+    let ticks_in_array = TICK_ARRAY_SIZE * pool_state.tick_spacing as i32;
+    let current_start_index =
+        get_tick_array_start_index(pool_state.tick_current, pool_state.tick_spacing);
+    let start_indexes = (0..=TICK_ARRAY_LOOKAHEAD)
+        .map(|i| {
+            if zero_for_one {
+                current_start_index - i * ticks_in_array
+            } else {
+                current_start_index + i * ticks_in_array
+            }
+        })
+        .filter(|start_index| {
+            *start_index >= math::MIN_TICK - ticks_in_array && *start_index <= math::MAX_TICK
+        })
+        .collect::<Vec<_>>();
+
+    let bitmap_extension = get_tick_array_bitmap_extension_pda(pool_id, &clmm_program);
+    let mut load_pubkeys = vec![bitmap_extension];
+    load_pubkeys.extend(
+        start_indexes
+            .iter()
+            .map(|start_index| get_tick_array_pda(pool_id, *start_index, &clmm_program)),
+    );
+    let rsps = rpc_client.get_multiple_accounts(&load_pubkeys).await?;
+
+    let mut swap_tick_arrays = SwapTickArrays {
+        bitmap_extension: rsps[0].as_ref().map(|_| bitmap_extension),
+        tick_arrays: vec![],
+    };
+    for (pubkey, rsp) in load_pubkeys.iter().zip(rsps.iter()).skip(1) {
+        let Some(account) = rsp else {
+            continue;
+        };
+        let tick_array: TickArrayState = decode_account(
+            &account.data,
+            CLMM_TICK_ARRAY_DISCRIMINATOR,
+            "clmm tick array",
+        )?;
+        // the program skips tick arrays without initialized ticks
+        if tick_array.initialized_tick_count > 0 {
+            swap_tick_arrays.tick_arrays.push((*pubkey, tick_array));
+        }
+    }
+    if swap_tick_arrays.tick_arrays.is_empty() {
+        return Err(NotFoundPool(format!(
+            "clmm pool {} has no liquidity in the swap direction",
+            pool_id
+        ))
+        .into());
+    }
+    Ok(swap_tick_arrays)
+}
+
+pub fn get_tick_array_pda(pool_id: &Pubkey, start_index: i32, program_id: &Pubkey) -> Pubkey {
+    let start_index = start_index.to_be_bytes();
+    let seeds = [
+        b"tick_array".as_ref(),
+        pool_id.as_ref(),
+        start_index.as_ref(),
+    ];
+    Pubkey::find_program_address(&seeds, program_id).0
+}
+
+pub fn get_tick_array_bitmap_extension_pda(pool_id: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    let seeds = [
+        b"pool_tick_array_bitmap_extension".as_ref(),
+        pool_id.as_ref(),
+    ];
+    Pubkey::find_program_address(&seeds, program_id).0
+}
@@ -0,0 +1,603 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use borsh::BorshDeserialize;
+use borsh_derive::{BorshDeserialize, BorshSerialize};
+use common::common_utils;
+use raydium_amm::math::U128;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+use spl_token::{amount_to_ui_amount, state::Account};
+use spl_token_client::token::TokenError;
+use tracing::{debug, error, info};
+
+use crate::{
+    amount::{self, Amount},
+    quote::{self, Quote},
+    slippage::Slippage,
+    swap::{NotFoundPool, SwapDirection, SwapInType, SwapOptions, Venue},
+    token,
+    tx::{self, SendResult},
+};
+
+pub const CPMM_PROGRAM: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+pub const CPMM_SWAP_BASE_INPUT_METHOD: u64 = 16011174931058048655;
+pub const CPMM_POOL_STATE_DISCRIMINATOR: u64 = 5106734359795461623;
+pub const CPMM_AMM_CONFIG_DISCRIMINATOR: u64 = 8010720436694414554;
+pub const CPMM_POOL_STATE_LEN: u64 = 637;
+pub const FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+// discriminator(8) + amm_config(32) + pool_creator(32) + token_0_vault(32) + token_1_vault(32) + lp_mint(32)
+const POOL_TOKEN_0_MINT_OFFSET: usize = 168;
+const POOL_TOKEN_1_MINT_OFFSET: usize = 200;
+
+pub struct RaydiumCpmm {
+    pub client: Arc<RpcClient>,
+    pub keypair: Arc<Keypair>,
+    pub pool_id: Option<String>,
+}
+
+impl RaydiumCpmm {
+    pub fn new(client: Arc<RpcClient>, keypair: Arc<Keypair>) -> Self {
+        Self {
+            client,
+            keypair,
+            pool_id: None,
+        }
+    }
+
+    pub fn with_pool_id(&mut self, pool_id: Option<String>) -> &mut Self {
+        self.pool_id = pool_id;
+        self
+    }
+
+    pub async fn swap(
+        &self,
+        mint_str: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        options: SwapOptions,
+    ) -> Result<SendResult> {
+        let slippage = options.slippage;
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out swap is not supported in raydium cpmm"));
+        }
+        let owner = self.keypair.pubkey();
+        let mint = Pubkey::from_str(mint_str)
+            .map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let native_mint = spl_token::native_mint::ID;
+        let cpmm_program = Pubkey::from_str(CPMM_PROGRAM)?;
+
+        let (pool_id, pool_state) =
+            get_pool_state(self.client.clone(), self.pool_id.as_deref(), Some(mint_str)).await?;
+        let amm_config = get_amm_config(
+            self.client.clone(),
+            &Pubkey::new_from_array(pool_state.amm_config),
+        )
+        .await?;
+        let reserves = get_pool_reserves(self.client.clone(), &pool_state).await?;
+        debug!("cpmm pool: {}, reserves: {:?}", pool_id, reserves);
+
+        // token 0 is the sol side of the pool
+        let sol_is_token_0 = pool_state.token_0_mint == native_mint.to_bytes();
+        let (token_in, token_out) = match swap_direction {
+            SwapDirection::Buy => (native_mint, mint),
+            SwapDirection::Sell => (mint, native_mint),
+        };
+        let (input_is_token_0, reserve_in, reserve_out) =
+            match (swap_direction.clone(), sol_is_token_0) {
+                (SwapDirection::Buy, true) | (SwapDirection::Sell, false) => {
+                    (true, reserves.0, reserves.1)
+                }
+                (SwapDirection::Buy, false) | (SwapDirection::Sell, true) => {
+                    (false, reserves.1, reserves.0)
+                }
+            };
+        let mint_program = Pubkey::new_from_array(if sol_is_token_0 {
+            pool_state.token_1_program
+        } else {
+            pool_state.token_0_program
+        });
+
+        let mint_ata = get_associated_token_address_with_program_id(&owner, &mint, &mint_program);
+
+        let mut create_instruction = None;
+        let mut close_instruction = None;
+
+        let (amount_specified, amount_ui_pretty) = match swap_direction {
+            SwapDirection::Buy => {
+                // Create base ATA if it doesn't exist.
+                match token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &token_out,
+                    &mint_ata,
+                )
+                .await
+                {
+                    Ok(_) => debug!("base ata exists. skipping creation.."),
+                    Err(TokenError::AccountNotFound) | Err(TokenError::AccountInvalidOwner) => {
+                        info!(
+                            "base ATA for mint {} does not exist. will be create",
+                            token_out
+                        );
+                        create_instruction = Some(create_associated_token_account(
+                            &owner,
+                            &owner,
+                            &token_out,
+                            &mint_program,
+                        ));
+                    }
+                    Err(error) => error!("error retrieving out ATA: {}", error),
+                }
+
+                if matches!(in_type, SwapInType::Usd | SwapInType::Pct) {
+                    return Err(quote::unresolved_buy(&in_type));
+                }
+                let amount = amount_in.to_raw(spl_token::native_mint::DECIMALS)?;
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, spl_token::native_mint::DECIMALS),
+                        spl_token::native_mint::DECIMALS,
+                    ),
+                )
+            }
+            SwapDirection::Sell => {
+                let in_account = token::get_account_info(
+                    self.client.clone(),
+                    self.keypair.clone(),
+                    &token_in,
+                    &mint_ata,
+                )
+                .await?;
+                let in_mint =
+                    token::get_mint_info(self.client.clone(), self.keypair.clone(), &token_in)
+                        .await?;
+                let amount = match in_type {
+                    SwapInType::Qty => {
+                        let amount = amount_in.to_raw(in_mint.base.decimals)?;
+                        amount::check_balance(amount, in_account.base.amount)?;
+                        amount
+                    }
+                    SwapInType::ExactOut => {
+                        return Err(anyhow!("exact out swap is not supported in raydium cpmm"))
+                    }
+                    SwapInType::Usd => return Err(anyhow!("usd amount only support buy")),
+                    SwapInType::Pct => {
+                        if amount_in.is_all() {
+                            // sell all, close ata
+                            info!("sell all. will be close ATA for mint {}", token_in);
+                            close_instruction = Some(spl_token_2022::instruction::close_account(
+                                &mint_program,
+                                &mint_ata,
+                                &owner,
+                                &owner,
+                                &[&owner],
+                            )?);
+                            in_account.base.amount
+                        } else {
+                            amount_in.share_of(in_account.base.amount)?
+                        }
+                    }
+                };
+                (
+                    amount,
+                    (
+                        amount_to_ui_amount(amount, in_mint.base.decimals),
+                        in_mint.base.decimals,
+                    ),
+                )
+            }
+        };
+
+        // Token-2022 transfer fees are withheld from what the pool receives on a sell and
+        // from what the owner receives on a buy, the min out is checked after the fee
+        let transfer_fee =
+            token::get_transfer_fee(self.client.clone(), self.keypair.clone(), &mint).await?;
+        let amount_out = match swap_direction {
+            SwapDirection::Buy => transfer_fee.post_fee_amount(swap_base_input(
+                amount_specified,
+                reserve_in,
+                reserve_out,
+                amm_config.trade_fee_rate,
+            )?),
+            SwapDirection::Sell => swap_base_input(
+                transfer_fee.post_fee_amount(amount_specified),
+                reserve_in,
+                reserve_out,
+                amm_config.trade_fee_rate,
+            )?,
+        };
+        let minimum_amount_out = slippage.min_amount(amount_out);
+
+        info!(
+            "swap: {}, value: {:?} -> {}",
+            token_in, amount_ui_pretty, token_out
+        );
+        info!(
+            "amount_specified: {}, amount_out: {}, minimum_amount_out: {}",
+            amount_specified, amount_out, minimum_amount_out
+        );
+
+        // build instructions
+        let mut instructions = vec![];
+        if let Some(create_instruction) = create_instruction {
+            instructions.push(create_instruction);
+        }
+        if amount_specified > 0 {
+            // sol <-> wsol support
+            let seed = &format!("{}", Keypair::new().pubkey())[..32];
+            let wsol_account = Pubkey::create_with_seed(&owner, seed, &spl_token::id())?;
+            let rent = self
+                .client
+                .get_minimum_balance_for_rent_exemption(Account::LEN)
+                .await?;
+            // if buy add amount_specified
+            let total_amount = match swap_direction {
+                SwapDirection::Buy => rent + amount_specified,
+                SwapDirection::Sell => rent,
+            };
+            instructions.push(system_instruction::create_account_with_seed(
+                &owner,
+                &wsol_account,
+                &owner,
+                seed,
+                total_amount,
+                Account::LEN as u64,
+                &spl_token::id(),
+            ));
+            instructions.push(spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &wsol_account,
+                &native_mint,
+                &owner,
+            )?);
+
+            let (user_input, user_output) = match swap_direction {
+                SwapDirection::Buy => (wsol_account, mint_ata),
+                SwapDirection::Sell => (mint_ata, wsol_account),
+            };
+            instructions.push(swap_base_input_instruction(
+                &cpmm_program,
+                &pool_id,
+                &pool_state,
+                &owner,
+                &user_input,
+                &user_output,
+                input_is_token_0,
+                amount_specified,
+                minimum_amount_out,
+            ));
+            // close wsol account
+            instructions.push(spl_token::instruction::close_account(
+                &spl_token::id(),
+                &wsol_account,
+                &owner,
+                &owner,
+                &[&owner],
+            )?);
+        }
+        if let Some(close_instruction) = close_instruction {
+            instructions.push(close_instruction);
+        }
+        if instructions.is_empty() {
+            return Err(anyhow!("instructions is empty, no tx required"));
+        }
+
+        tx::new_signed_and_send(&self.client, &self.keypair, instructions, options.send).await
+    }
+
+    pub async fn quote(
+        &self,
+        mint_str: &str,
+        amount_in: Amount,
+        swap_direction: SwapDirection,
+        in_type: SwapInType,
+        slippage: Slippage,
+    ) -> Result<Quote> {
+        if matches!(in_type, SwapInType::ExactOut) {
+            return Err(anyhow!("exact out swap is not supported in raydium cpmm"));
+        }
+        let mint = Pubkey::from_str(mint_str)
+            .map_err(|e| anyhow!("failed to parse mint pubkey: {}", e))?;
+        let native_mint = spl_token::native_mint::ID;
+
+        let (pool_id, pool_state) =
+            get_pool_state(self.client.clone(), self.pool_id.as_deref(), Some(mint_str)).await?;
+        let amm_config = get_amm_config(
+            self.client.clone(),
+            &Pubkey::new_from_array(pool_state.amm_config),
+        )
+        .await?;
+        let reserves = get_pool_reserves(self.client.clone(), &pool_state).await?;
+        let sol_is_token_0 = pool_state.token_0_mint == native_mint.to_bytes();
+        let (reserve_in, reserve_out) = match (swap_direction.clone(), sol_is_token_0) {
+            (SwapDirection::Buy, true) | (SwapDirection::Sell, false) => (reserves.0, reserves.1),
+            (SwapDirection::Buy, false) | (SwapDirection::Sell, true) => (reserves.1, reserves.0),
+        };
+
+        let (amount_specified, decimals) = quote::resolve_amount(
+            self.client.clone(),
+            self.keypair.clone(),
+            &mint,
+            amount_in,
+            &swap_direction,
+            &in_type,
+        )
+        .await?;
+        let mut quote = Quote::new(
+            Venue::RaydiumCpmm,
+            &pool_id,
+            &mint,
+            decimals,
+            &swap_direction,
+        );
+        quote.amount_in = amount_specified;
+        quote.amount_out = swap_base_input(
+            amount_specified,
+            reserve_in,
+            reserve_out,
+            amm_config.trade_fee_rate,
+        )?;
+        // the trade fee is taken from the input token
+        quote.fee_amount = (amount_specified as u128 * amm_config.trade_fee_rate as u128)
+            .div_ceil(FEE_RATE_DENOMINATOR as u128) as u64;
+        quote.fee_mint = quote.input_mint.clone();
+        quote.reserve_in = reserve_in;
+        quote.reserve_out = reserve_out;
+        quote.price_impact_pct = quote::price_impact_pct(
+            amount_specified - quote.fee_amount,
+            quote.amount_out,
+            reserve_out as f64 / reserve_in as f64,
+        );
+        Ok(quote.with_slippage(slippage, false))
+    }
+}
+
+/// Output amount for an exact input swap, the trade fee is taken from the input.
+pub fn swap_base_input(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    trade_fee_rate: u64,
+) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(anyhow!("invalid cpmm pool reserves"));
+    }
+    let trade_fee =
+        (amount_in as u128 * trade_fee_rate as u128).div_ceil(FEE_RATE_DENOMINATOR as u128) as u64;
+    let amount_in_less_fee = amount_in
+        .checked_sub(trade_fee)
+        .ok_or(anyhow!("cpmm trade fee exceeds amount in"))?;
+    let amount_out = U128::from(reserve_out) * U128::from(amount_in_less_fee)
+        / (U128::from(reserve_in) + U128::from(amount_in_less_fee));
+    Ok(amount_out.as_u64())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap_base_input_instruction(
+    cpmm_program: &Pubkey,
+    pool_id: &Pubkey,
+    pool_state: &CpmmPoolState,
+    payer: &Pubkey,
+    user_input: &Pubkey,
+    user_output: &Pubkey,
+    input_is_token_0: bool,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    let token_0 = (
+        pool_state.token_0_vault,
+        pool_state.token_0_program,
+        pool_state.token_0_mint,
+    );
+    let token_1 = (
+        pool_state.token_1_vault,
+        pool_state.token_1_program,
+        pool_state.token_1_mint,
+    );
+    let (input, output) = if input_is_token_0 {
+        (token_0, token_1)
+    } else {
+        (token_1, token_0)
+    };
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(get_authority_pda(cpmm_program), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pool_state.amm_config), false),
+        AccountMeta::new(*pool_id, false),
+        AccountMeta::new(*user_input, false),
+        AccountMeta::new(*user_output, false),
+        AccountMeta::new(Pubkey::new_from_array(input.0), false),
+        AccountMeta::new(Pubkey::new_from_array(output.0), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(input.1), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(output.1), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(input.2), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(output.2), false),
+        AccountMeta::new(Pubkey::new_from_array(pool_state.observation_key), false),
+    ];
+    Instruction::new_with_bincode(
+        *cpmm_program,
+        &(CPMM_SWAP_BASE_INPUT_METHOD, amount_in, minimum_amount_out),
+        accounts,
+    )
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct CpmmPoolState {
+    pub discriminator: u64,
+    pub amm_config: [u8; 32],
+    pub pool_creator: [u8; 32],
+    pub token_0_vault: [u8; 32],
+    pub token_1_vault: [u8; 32],
+    pub lp_mint: [u8; 32],
+    pub token_0_mint: [u8; 32],
+    pub token_1_mint: [u8; 32],
+    pub token_0_program: [u8; 32],
+    pub token_1_program: [u8; 32],
+    pub observation_key: [u8; 32],
+    pub auth_bump: u8,
+    pub status: u8,
+    pub lp_mint_decimals: u8,
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+    pub lp_supply: u64,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    pub open_time: u64,
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct CpmmAmmConfig {
+    pub discriminator: u64,
+    pub bump: u8,
+    pub disable_create_pool: bool,
+    pub index: u16,
+    pub trade_fee_rate: u64,
+    pub protocol_fee_rate: u64,
+    pub fund_fee_rate: u64,
+    pub create_pool_fee: u64,
+}
+
+fn decode_account<T: BorshDeserialize>(data: &[u8], discriminator: u64, name: &str) -> Result<T> {
+    if data.len() < 8 || u64::from_le_bytes(data[..8].try_into()?) != discriminator {
+        return Err(anyhow!("invalid {} account discriminator", name));
+    }
+    // only read the prefix we know about, the rest is padding
+    T::deserialize(&mut &data[..])
+        .map_err(|e| anyhow!("Failed to deserialize {} account: {}", name, e))
+}
+
+pub async fn get_pool_state(
+    rpc_client: Arc<RpcClient>,
+    pool_id: Option<&str>,
+    mint: Option<&str>,
+) -> Result<(Pubkey, CpmmPoolState)> {
+    if let Some(pool_id) = pool_id {
+        debug!("finding cpmm pool state by pool_id: {}", pool_id);
+        let pool_id = Pubkey::from_str(pool_id)?;
+        let pool_data = rpc_client.get_account_data(&pool_id).await?;
+        let pool_state =
+            decode_account(&pool_data, CPMM_POOL_STATE_DISCRIMINATOR, "cpmm pool state")?;
+        Ok((pool_id, pool_state))
+    } else if let Some(mint) = mint {
+        get_pool_state_by_mint(rpc_client, mint).await
+    } else {
+        Err(NotFoundPool("cpmm pool state not found".to_string()).into())
+    }
+}
+
+pub async fn get_pool_state_by_mint(
+    rpc_client: Arc<RpcClient>,
+    mint: &str,
+) -> Result<(Pubkey, CpmmPoolState)> {
+    debug!("finding cpmm pool state by mint: {}", mint);
+    let mint = Pubkey::from_str(mint)?;
+    let native_mint = spl_token::native_mint::ID;
+    let cpmm_program = Pubkey::from_str(CPMM_PROGRAM)?;
+    // (token_0_mint, token_1_mint)
+    let pairs = vec![(mint, native_mint), (native_mint, mint)];
+    for (token_0_mint, token_1_mint) in pairs {
+        let filters = Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                POOL_TOKEN_0_MINT_OFFSET,
+                &token_0_mint.to_bytes(),
+            )),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                POOL_TOKEN_1_MINT_OFFSET,
+                &token_1_mint.to_bytes(),
+            )),
+            RpcFilterType::DataSize(CPMM_POOL_STATE_LEN),
+        ]);
+        let pools =
+            crate::rpc::get_program_accounts_with_filters(&rpc_client, cpmm_program, filters)
+                .await?;
+        if let Some((pool_id, account)) = pools.first() {
+            let pool_state = decode_account(
+                &account.data,
+                CPMM_POOL_STATE_DISCRIMINATOR,
+                "cpmm pool state",
+            )?;
+            return Ok((*pool_id, pool_state));
+        }
+    }
+    Err(NotFoundPool("cpmm pool state not found".to_string()).into())
+}
+
+pub async fn get_amm_config(
+    rpc_client: Arc<RpcClient>,
+    amm_config: &Pubkey,
+) -> Result<CpmmAmmConfig> {
+    let amm_config_data = rpc_client.get_account_data(amm_config).await?;
+    decode_account(
+        &amm_config_data,
+        CPMM_AMM_CONFIG_DISCRIMINATOR,
+        "cpmm amm config",
+    )
+}
+
+/// Returns the tradable (token_0, token_1) reserves, vault balances minus accrued fees.
+pub async fn get_pool_reserves(
+    rpc_client: Arc<RpcClient>,
+    pool_state: &CpmmPoolState,
+) -> Result<(u64, u64)> {
+    let load_pubkeys = vec![
+        Pubkey::new_from_array(pool_state.token_0_vault),
+        Pubkey::new_from_array(pool_state.token_1_vault),
+    ];
+    let rsps = rpc_client.get_multiple_accounts(&load_pubkeys).await?;
+    let mut vault_amounts = vec![];
+    for rsp in rsps {
+        let account = rsp.ok_or(anyhow!("cpmm pool vault not found"))?;
+        vault_amounts.push(common_utils::unpack_token(&account.data)?.base.amount);
+    }
+    let token_0 = vault_amounts[0]
+        .saturating_sub(pool_state.protocol_fees_token_0)
+        .saturating_sub(pool_state.fund_fees_token_0);
+    let token_1 = vault_amounts[1]
+        .saturating_sub(pool_state.protocol_fees_token_1)
+        .saturating_sub(pool_state.fund_fees_token_1);
+    Ok((token_0, token_1))
+}
+
+pub fn get_authority_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault_and_lp_mint_auth_seed"], program_id).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_base_input() {
+        // 0.25% trade fee
+        let amount_out = swap_base_input(1_000_000, 100_000_000, 200_000_000, 2500).unwrap();
+        // 997500 enters the curve
+        assert_eq!(amount_out, 200_000_000 * 997_500 / (100_000_000 + 997_500));
+        assert!(swap_base_input(1, 0, 1, 2500).is_err());
+    }
+
+    #[test]
+    fn test_authority_pda() {
+        assert_eq!(
+            get_authority_pda(&Pubkey::from_str(CPMM_PROGRAM).unwrap()).to_string(),
+            "GpMZbSM2GgvTKHJirzeGfMFoaZ8UR2X7F4v8vHTvxFbL"
+        );
+    }
+}
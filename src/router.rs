@@ -0,0 +1,247 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use futures_util::future::join_all;
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use tracing::{debug, info, warn};
+
+use crate::{
+    amount::Amount,
+    constants::HOP_MINTS,
+    pump::get_pump_info,
+    pump_amm,
+    quote::{quote_venue, Quote},
+    raydium, raydium_clmm, raydium_cpmm,
+    slippage::Slippage,
+    swap::{NotFoundPool, SwapDirection, SwapInType, Venue},
+};
+
+/// The quote a swap is executed on, and the ones it beat.
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    pub quote: Quote,
+    pub losing_quotes: Vec<Quote>,
+}
+
+/// `None` when the venue has no pool for the mint, any other error is returned as is.
+fn found<T>(result: Result<T>, venue: Venue) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if NotFoundPool::is(&err) => {
+            debug!("{:?} pool not found: {}", venue, err);
+            Ok(None)
+        }
+        Err(err) => Err(err.context(format!("failed to look up {:?} pool", venue))),
+    }
+}
+
+/// Raydium AMM v4 pools pairing the mint with `quote_mint` that are open for swaps.
+async fn raydium_candidates(
+    client: Arc<RpcClient>,
+    mint: &str,
+    quote_mint: &Pubkey,
+) -> Result<Vec<(Venue, Option<String>)>> {
+    let pools = found(
+        raydium::get_ranked_pools(client, mint, quote_mint).await,
+        Venue::RaydiumAmm,
+    )?
+    .unwrap_or_default();
+    Ok(pools
+        .into_iter()
+        .filter(|pool| pool.liquidity.tradable)
+        .map(|pool| (Venue::RaydiumAmm, Some(pool.pool_id.to_string())))
+        .collect())
+}
+
+/// Every venue and pool that can trade the mint against `quote_mint`. Only Raydium AMM v4
+/// pools trade against other quote mints than SOL. A token without any SOL pool is routed
+/// through its USDC or USDT pool.
+pub async fn candidates(
+    client: Arc<RpcClient>,
+    mint: &str,
+    quote_mint: &Pubkey,
+) -> Result<Vec<(Venue, Option<String>)>> {
+    if *quote_mint != spl_token::native_mint::ID {
+        let candidates = raydium_candidates(client, mint, quote_mint).await?;
+        if candidates.is_empty() {
+            return Err(NotFoundPool(format!(
+                "no raydium amm pool trades {} against {}",
+                mint, quote_mint
+            ))
+            .into());
+        }
+        return Ok(candidates);
+    }
+    let mut candidates = Vec::new();
+    let pump_info = found(get_pump_info(client.clone(), mint).await, Venue::Pump)?;
+    // tokens still on the bonding curve trade nowhere else
+    if pump_info.is_some_and(|pump_info| !pump_info.complete) {
+        return Ok(vec![(Venue::Pump, None)]);
+    }
+    let mint_pubkey = Pubkey::from_str(mint)?;
+    let pump_amm_pool = pump_amm::get_pool_account_by_mint(client.clone(), &mint_pubkey);
+    if let Some((pool_id, _)) = found(pump_amm_pool.await, Venue::PumpAmm)? {
+        candidates.push((Venue::PumpAmm, Some(pool_id.to_string())));
+    }
+    candidates.extend(raydium_candidates(client.clone(), mint, quote_mint).await?);
+    let cpmm_pool = raydium_cpmm::get_pool_state_by_mint(client.clone(), mint);
+    if let Some((pool_id, _)) = found(cpmm_pool.await, Venue::RaydiumCpmm)? {
+        candidates.push((Venue::RaydiumCpmm, Some(pool_id.to_string())));
+    }
+    let clmm_pool = raydium_clmm::get_pool_state_by_mint(client.clone(), mint);
+    if let Some((pool_id, _)) = found(clmm_pool.await, Venue::RaydiumClmm)? {
+        candidates.push((Venue::RaydiumClmm, Some(pool_id.to_string())));
+    }
+    if candidates.is_empty() {
+        // no SOL pool, go through the deepest stable pool
+        for hop_mint in HOP_MINTS {
+            let hop_candidates =
+                raydium_candidates(client.clone(), mint, &Pubkey::from_str(hop_mint)?).await?;
+            candidates.extend(hop_candidates.into_iter().take(1));
+        }
+    }
+    if candidates.is_empty() {
+        return Err(NotFoundPool(format!("no venue trades {}", mint)).into());
+    }
+    Ok(candidates)
+}
+
+/// Whether `a` is a better execution than `b`: more output after fees, or for exact out
+/// swaps less input for the same output.
+pub fn is_better(a: &Quote, b: &Quote, exact_out: bool) -> bool {
+    if exact_out {
+        a.amount_in < b.amount_in
+    } else {
+        a.amount_out > b.amount_out
+    }
+}
+
+/// Splits quotes into the best one and the rest.
+pub fn select(quotes: Vec<Quote>, exact_out: bool) -> Option<Route> {
+    let mut quotes = quotes.into_iter();
+    let mut best = quotes.next()?;
+    let mut losing_quotes = Vec::new();
+    for quote in quotes {
+        if is_better(&quote, &best, exact_out) {
+            losing_quotes.push(std::mem::replace(&mut best, quote));
+        } else {
+            losing_quotes.push(quote);
+        }
+    }
+    Some(Route {
+        quote: best,
+        losing_quotes,
+    })
+}
+
+/// Quotes every venue trading the mint and picks the one with the best net output.
+#[allow(clippy::too_many_arguments)]
+pub async fn best_route(
+    client: Arc<RpcClient>,
+    wallet: Arc<Keypair>,
+    mint: &str,
+    amount_in: Amount,
+    swap_direction: SwapDirection,
+    in_type: SwapInType,
+    slippage: Slippage,
+    quote_mint: Pubkey,
+) -> Result<Route> {
+    let exact_out = matches!(in_type, SwapInType::ExactOut);
+    let candidates = candidates(client.clone(), mint, &quote_mint).await?;
+    let results = join_all(candidates.iter().map(|(venue, pool_id)| {
+        quote_venue(
+            client.clone(),
+            wallet.clone(),
+            *venue,
+            pool_id.clone(),
+            mint,
+            amount_in,
+            swap_direction.clone(),
+            in_type.clone(),
+            slippage,
+            quote_mint,
+        )
+    }))
+    .await;
+    let mut quotes = Vec::new();
+    let mut errors = Vec::new();
+    for ((venue, pool_id), result) in candidates.into_iter().zip(results) {
+        match result {
+            Ok(quote) => quotes.push(quote),
+            Err(err) => {
+                warn!("{} quote failed, pool: {:?}, err: {}", venue, pool_id, err);
+                errors.push(format!("{}: {}", venue, err));
+            }
+        }
+    }
+    let route = select(quotes, exact_out).ok_or(anyhow!(
+        "NoQuote: no venue quoted {}: {}",
+        mint,
+        errors.join("; ")
+    ))?;
+    info!(
+        "route to {} pool {}, beat {} quotes",
+        route.quote.venue,
+        route.quote.pool,
+        route.losing_quotes.len()
+    );
+    Ok(route)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(venue: Venue, amount_in: u64, amount_out: u64) -> Quote {
+        let mut quote = Quote::new(
+            venue,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            6,
+            &SwapDirection::Buy,
+        );
+        quote.amount_in = amount_in;
+        quote.amount_out = amount_out;
+        quote
+    }
+
+    #[test]
+    fn test_select() {
+        assert!(select(vec![], false).is_none());
+
+        let quotes = vec![
+            quote(Venue::PumpAmm, 1_000, 900),
+            quote(Venue::RaydiumAmm, 1_000, 950),
+            quote(Venue::RaydiumCpmm, 1_000, 920),
+        ];
+        let route = select(quotes, false).unwrap();
+        assert_eq!(route.quote.venue, Venue::RaydiumAmm);
+        assert_eq!(route.losing_quotes.len(), 2);
+
+        let quotes = vec![
+            quote(Venue::PumpAmm, 1_050, 1_000),
+            quote(Venue::RaydiumAmm, 1_100, 1_000),
+        ];
+        let route = select(quotes, true).unwrap();
+        assert_eq!(route.quote.venue, Venue::PumpAmm);
+        assert_eq!(route.losing_quotes[0].venue, Venue::RaydiumAmm);
+    }
+
+    #[test]
+    fn test_found() {
+        assert_eq!(found(Ok(1), Venue::Pump).unwrap(), Some(1));
+        let not_found: Result<u64> = Err(NotFoundPool("pool state not found".to_string()).into());
+        assert_eq!(found(not_found, Venue::RaydiumAmm).unwrap(), None);
+        let rpc_err: Result<u64> = Err(anyhow!("error sending request"));
+        assert!(found(rpc_err, Venue::RaydiumCpmm).is_err());
+        // the type decides, not the message
+        let wrapped: Result<u64> =
+            Err(anyhow::Error::from(NotFoundPool("no pool".to_string()))
+                .context("failed to find pool"));
+        assert_eq!(found(wrapped, Venue::RaydiumClmm).unwrap(), None);
+        let lookalike: Result<u64> = Err(anyhow!("NotFoundPool: from a message"));
+        assert!(found(lookalike, Venue::PumpAmm).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::{RpcClient as BlockingRpcClient, RpcClientConfig},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::RpcFilterType,
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use tokio::runtime::Handle;
+
+/// Accounts of `program` matching `filters`, as `common::rpc` reads them, on the nonblocking
+/// client.
+pub async fn get_program_accounts_with_filters(
+    client: &RpcClient,
+    program: Pubkey,
+    filters: Option<Vec<RpcFilterType>>,
+) -> Result<Vec<(Pubkey, Account)>> {
+    let accounts = client
+        .get_program_accounts_with_config(
+            &program,
+            RpcProgramAccountsConfig {
+                filters,
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                with_context: Some(false),
+            },
+        )
+        .await?;
+    Ok(accounts)
+}
+
+/// A blocking client for libraries such as amm-cli that only read through one. Its
+/// requests are sent by `client` on the runtime of `handle`, so they share its connections
+/// and, for a pooled client, its endpoint ranking and retries. Build and use it off the
+/// runtime, e.g. in `spawn_blocking`.
+pub fn blocking_client(client: Arc<RpcClient>, handle: Handle) -> BlockingRpcClient {
+    let config = RpcClientConfig::with_commitment(client.commitment());
+    BlockingRpcClient::new_sender(ClientSender { client, handle }, config)
+}
+
+struct ClientSender {
+    client: Arc<RpcClient>,
+    handle: Handle,
+}
+
+#[async_trait]
+impl RpcSender for ClientSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let client = self.client.clone();
+        self.handle
+            .spawn(async move { client.send(request, params).await })
+            .await
+            .map_err(|err| ClientErrorKind::Custom(err.to_string()))?
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.client.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.client.url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_blocking_client() {
+        let client = Arc::new(RpcClient::new_mock("succeeds".to_string()));
+        let handle = Handle::current();
+        let slot =
+            tokio::task::spawn_blocking(move || blocking_client(client, handle).get_slot().ok())
+                .await
+                .unwrap();
+        assert_eq!(slot, Some(0));
+    }
+}
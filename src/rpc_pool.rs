@@ -0,0 +1,361 @@
+use std::{
+    env,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use serde::Serialize;
+use serde_json::{json, Value};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_custom_error::{
+        JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    },
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::commitment_config::CommitmentConfig;
+use tracing::{debug, warn};
+
+use crate::{endpoint_host, parse_rpc_urls};
+
+/// Weight of the newest sample in the latency and error rate moving averages.
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// Thresholds and timing of the pool, from the environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcPoolConfig {
+    /// RPC_HEALTH_INTERVAL_MS, how often every endpoint is asked for its slot
+    pub health_interval: Duration,
+    /// RPC_MAX_SLOT_LAG, an endpoint further behind the highest slot is unhealthy
+    pub max_slot_lag: u64,
+    /// RPC_MAX_ERROR_RATE, an endpoint failing more often is unhealthy
+    pub max_error_rate: f64,
+    /// RPC_MAX_ATTEMPTS, endpoints a request is tried on before its error is returned
+    pub max_attempts: usize,
+}
+
+impl Default for RpcPoolConfig {
+    fn default() -> Self {
+        Self {
+            health_interval: Duration::from_millis(5_000),
+            max_slot_lag: 25,
+            max_error_rate: 0.5,
+            max_attempts: 3,
+        }
+    }
+}
+
+impl RpcPoolConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let var = |key: &str| env::var(key).ok().map(|v| v.trim().to_string());
+        Self {
+            health_interval: var("RPC_HEALTH_INTERVAL_MS")
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.health_interval),
+            max_slot_lag: var("RPC_MAX_SLOT_LAG")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_slot_lag),
+            max_error_rate: var("RPC_MAX_ERROR_RATE")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_error_rate),
+            max_attempts: var("RPC_MAX_ATTEMPTS")
+                .and_then(|v| v.parse().ok())
+                .filter(|attempts| *attempts > 0)
+                .unwrap_or(default.max_attempts),
+        }
+    }
+}
+
+/// What the pool knows about an endpoint, from its requests and health checks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EndpointHealth {
+    /// host of the url, the url itself may hold an api key
+    pub host: String,
+    /// moving average of the request latency
+    pub latency_ms: f64,
+    /// moving average share of requests that failed, 0 to 1
+    pub error_rate: f64,
+    /// last slot the endpoint reported, 0 before the first health check
+    pub slot: u64,
+    /// slots behind the highest slot any endpoint reported
+    pub slot_lag: u64,
+    pub requests: u64,
+    pub errors: u64,
+    pub healthy: bool,
+}
+
+impl EndpointHealth {
+    fn record(&mut self, elapsed: Duration, ok: bool) {
+        let latency_ms = elapsed.as_secs_f64() * 1_000.0;
+        let failed = if ok { 0.0 } else { 1.0 };
+        if self.requests == 0 {
+            self.latency_ms = latency_ms;
+            self.error_rate = failed;
+        } else {
+            self.latency_ms += EWMA_WEIGHT * (latency_ms - self.latency_ms);
+            self.error_rate += EWMA_WEIGHT * (failed - self.error_rate);
+        }
+        self.requests += 1;
+        if !ok {
+            self.errors += 1;
+        }
+    }
+
+    fn update_healthy(&mut self, config: &RpcPoolConfig) {
+        self.healthy =
+            self.slot_lag <= config.max_slot_lag && self.error_rate <= config.max_error_rate;
+    }
+}
+
+struct Endpoint {
+    sender: HttpSender,
+    health: Mutex<EndpointHealth>,
+}
+
+impl Endpoint {
+    /// The health of the endpoint, still readable after a thread panicked holding it.
+    fn health(&self) -> MutexGuard<'_, EndpointHealth> {
+        self.health.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// RPC_ENDPOINTS behind one `RpcClient`: reads go to the healthiest endpoint and transient
+/// errors are retried on the next one.
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+    config: RpcPoolConfig,
+}
+
+impl RpcPool {
+    pub fn new(urls: Vec<String>, config: RpcPoolConfig) -> Result<Arc<Self>> {
+        if urls.is_empty() {
+            return Err(anyhow!("No RPC endpoints configured"));
+        }
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                health: Mutex::new(EndpointHealth {
+                    host: endpoint_host(&url),
+                    healthy: true,
+                    ..Default::default()
+                }),
+                sender: HttpSender::new(url),
+            })
+            .collect();
+        Ok(Arc::new(Self { endpoints, config }))
+    }
+
+    /// Pool of RPC_ENDPOINTS.
+    pub fn from_env() -> Result<Arc<Self>> {
+        let urls = parse_rpc_urls(&env::var("RPC_ENDPOINTS")?);
+        Self::new(urls, RpcPoolConfig::from_env())
+    }
+
+    /// Client sending every request through the pool.
+    pub fn client(self: &Arc<Self>) -> Arc<RpcClient> {
+        Arc::new(RpcClient::new_sender(
+            PoolSender(self.clone()),
+            RpcClientConfig::with_commitment(CommitmentConfig::default()),
+        ))
+    }
+
+    /// Checks every endpoint each RPC_HEALTH_INTERVAL_MS in the background.
+    pub fn spawn_health_checks(self: &Arc<Self>) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(pool.config.health_interval);
+            loop {
+                interval.tick().await;
+                pool.check_health().await;
+            }
+        });
+    }
+
+    /// Asks every endpoint for its slot, recording the latency and the lag behind the
+    /// highest slot.
+    pub async fn check_health(&self) {
+        let checks = self.endpoints.iter().map(|endpoint| async move {
+            let start = Instant::now();
+            let slot = endpoint
+                .sender
+                .send(RpcRequest::GetSlot, json!([{"commitment": "processed"}]))
+                .await
+                .map_err(|err| err.to_string())
+                .and_then(|slot| slot.as_u64().ok_or(format!("invalid slot {}", slot)));
+            (start.elapsed(), slot)
+        });
+        let results = join_all(checks).await;
+        let max_slot = results
+            .iter()
+            .filter_map(|(_, slot)| slot.as_ref().ok())
+            .max()
+            .copied()
+            .unwrap_or_default();
+        for (endpoint, (elapsed, slot)) in self.endpoints.iter().zip(results) {
+            let mut health = endpoint.health();
+            match slot {
+                Ok(slot) => {
+                    health.record(elapsed, true);
+                    health.slot = slot;
+                }
+                Err(err) => {
+                    health.record(elapsed, false);
+                    debug!("health check of {} failed: {}", health.host, err);
+                }
+            }
+            health.slot_lag = max_slot.saturating_sub(health.slot);
+            health.update_healthy(&self.config);
+            if !health.healthy {
+                warn!("rpc endpoint unhealthy: {:?}", *health);
+            }
+        }
+    }
+
+    /// Health of every endpoint, in RPC_ENDPOINTS order.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.health().clone())
+            .collect()
+    }
+
+    /// Endpoint indexes, healthy ones first, then by latency.
+    fn ranked(&self) -> Vec<usize> {
+        rank(&self.health())
+    }
+}
+
+/// Indexes of `health`, healthy endpoints first, then by latency and error rate.
+fn rank(health: &[EndpointHealth]) -> Vec<usize> {
+    let mut indexes = (0..health.len()).collect::<Vec<_>>();
+    indexes.sort_by(|a, b| {
+        let (a, b) = (&health[*a], &health[*b]);
+        b.healthy
+            .cmp(&a.healthy)
+            .then(a.error_rate.total_cmp(&b.error_rate))
+            .then(a.latency_ms.total_cmp(&b.latency_ms))
+    });
+    indexes
+}
+
+/// Errors another endpoint may not return: the endpoint could not be reached, limited our
+/// rate, or is behind.
+pub fn is_transient(err: &ClientError) -> bool {
+    match &err.kind {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+                || *code == JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+        }
+        _ => false,
+    }
+}
+
+struct PoolSender(Arc<RpcPool>);
+
+#[async_trait]
+impl RpcSender for PoolSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let pool = &self.0;
+        let mut last_err = None;
+        for index in pool.ranked().into_iter().take(pool.config.max_attempts) {
+            let endpoint = &pool.endpoints[index];
+            let start = Instant::now();
+            let result = endpoint.sender.send(request, params.clone()).await;
+            // an error the endpoint answered with, such as a failed simulation, is not its fault
+            let transient = result.as_ref().err().is_some_and(is_transient);
+            let mut health = endpoint.health();
+            health.record(start.elapsed(), !transient);
+            health.update_healthy(&pool.config);
+            if !transient {
+                return result;
+            }
+            let err = result.unwrap_err();
+            warn!("{} on {} failed: {}", request, health.host, err);
+            last_err = Some(err);
+        }
+        Err(last_err.unwrap_or_else(|| ClientErrorKind::Custom("no rpc endpoints".into()).into()))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.0
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.sender.get_transport_stats())
+            .fold(RpcTransportStats::default(), |mut total, stats| {
+                total.request_count += stats.request_count;
+                total.elapsed_time += stats.elapsed_time;
+                total.rate_limited_time += stats.rate_limited_time;
+                total
+            })
+    }
+
+    fn url(&self) -> String {
+        let pool = &self.0;
+        pool.endpoints[pool.ranked()[0]].sender.url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use solana_client::rpc_request::RpcResponseErrorData;
+
+    use super::*;
+
+    #[test]
+    fn test_endpoint_ranking() {
+        let config = RpcPoolConfig::default();
+        let mut fast = EndpointHealth::default();
+        fast.record(Duration::from_millis(50), true);
+        let mut slow = EndpointHealth::default();
+        slow.record(Duration::from_millis(400), true);
+        slow.record(Duration::from_millis(200), true);
+        assert_eq!(slow.latency_ms, 360.0);
+        let mut lagging = fast.clone();
+        lagging.slot_lag = 100;
+        let mut failing = fast.clone();
+        for _ in 0..5 {
+            failing.record(Duration::from_millis(50), false);
+        }
+        assert_eq!(failing.errors, 5);
+        for health in [&mut fast, &mut slow, &mut lagging, &mut failing] {
+            health.update_healthy(&config);
+        }
+        assert!(fast.healthy && slow.healthy);
+        assert!(!lagging.healthy && !failing.healthy);
+        assert_eq!(rank(&[failing, slow, lagging, fast]), vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let io_err = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(is_transient(&ClientErrorKind::Io(io_err).into()));
+        let response_err = |code| {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code,
+                message: String::new(),
+                data: RpcResponseErrorData::Empty,
+            })
+            .into()
+        };
+        assert!(is_transient(&response_err(
+            JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        )));
+        assert!(!is_transient(&response_err(-32602)));
+        assert!(!is_transient(
+            &ClientErrorKind::Custom("account not found".into()).into()
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+use std::{env, fmt, str::FromStr};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::Deserialize;
+
+use crate::amount::MAX_BPS;
+
+/// Used when neither the request nor SLIPPAGE sets one, in percent.
+const DEFAULT_SLIPPAGE_PCT: u64 = 5;
+
+/// How far a swap may fall behind its quote, in basis points, at most 100%.
+///
+/// Parsed from `50bps`, `0.5%` or a bare number of percent, as SLIPPAGE has always been.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "SlippageInput")]
+pub struct Slippage(u64);
+
+impl Slippage {
+    pub fn from_bps(bps: u64) -> Result<Self, SlippageError> {
+        if bps > MAX_BPS {
+            return Err(SlippageError::OutOfRange(bps.to_string()));
+        }
+        Ok(Self(bps))
+    }
+
+    /// Percent with up to two decimals, one basis point.
+    pub fn from_pct(pct: Decimal) -> Result<Self, SlippageError> {
+        if pct.is_sign_negative() && !pct.is_zero() {
+            return Err(SlippageError::OutOfRange(format!("{}%", pct)));
+        }
+        let bps = pct * Decimal::from(100);
+        if !bps.fract().is_zero() {
+            return Err(SlippageError::Precision(pct));
+        }
+        let bps = bps
+            .to_u64()
+            .ok_or(SlippageError::OutOfRange(format!("{}%", pct)))?;
+        Self::from_bps(bps).map_err(|_| SlippageError::OutOfRange(format!("{}%", pct)))
+    }
+
+    /// SLIPPAGE from the environment, 5% when unset.
+    pub fn from_env() -> Result<Self, SlippageError> {
+        match env::var("SLIPPAGE") {
+            Ok(slippage) => slippage.parse(),
+            Err(_) => Self::from_bps(DEFAULT_SLIPPAGE_PCT * 100),
+        }
+    }
+
+    pub fn bps(&self) -> u64 {
+        self.0
+    }
+
+    /// Least output accepted for a quoted `amount`, rounded down.
+    pub fn min_amount(&self, amount: u64) -> u64 {
+        (amount as u128 * (MAX_BPS - self.0) as u128 / MAX_BPS as u128) as u64
+    }
+
+    /// Most input spent for a quoted `amount`, rounded up, so the bound never tolerates less
+    /// than the requested slippage.
+    pub fn max_amount(&self, amount: u64) -> u64 {
+        (amount as u128 * (MAX_BPS + self.0) as u128)
+            .div_ceil(MAX_BPS as u128)
+            .min(u64::MAX as u128) as u64
+    }
+
+    /// Largest quoted input whose [`max_amount`](Self::max_amount) fits in `budget`.
+    pub fn max_amount_within(&self, budget: u64) -> u64 {
+        (budget as u128 * MAX_BPS as u128 / (MAX_BPS + self.0) as u128) as u64
+    }
+}
+
+impl Default for Slippage {
+    fn default() -> Self {
+        Self(DEFAULT_SLIPPAGE_PCT * 100)
+    }
+}
+
+impl FromStr for Slippage {
+    type Err = SlippageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || SlippageError::Invalid(s.to_string());
+        if let Some(bps) = s.strip_suffix("bps") {
+            let bps = bps.trim().parse::<u64>().map_err(|_| invalid())?;
+            return Self::from_bps(bps);
+        }
+        let pct = s.strip_suffix('%').unwrap_or(s).trim();
+        Self::from_pct(Decimal::from_str(pct).map_err(|_| invalid())?)
+    }
+}
+
+impl fmt::Display for Slippage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", Decimal::new(self.0 as i64, 2).normalize())
+    }
+}
+
+/// A number of percent, or a string such as `50bps` or `0.5%`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SlippageInput {
+    Pct(Decimal),
+    Str(String),
+}
+
+impl TryFrom<SlippageInput> for Slippage {
+    type Error = SlippageError;
+
+    fn try_from(input: SlippageInput) -> Result<Self, Self::Error> {
+        match input {
+            SlippageInput::Pct(pct) => Self::from_pct(pct),
+            SlippageInput::Str(s) => s.parse(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlippageError {
+    Invalid(String),
+    OutOfRange(String),
+    /// finer than a basis point
+    Precision(Decimal),
+}
+
+impl fmt::Display for SlippageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlippageError::Invalid(s) => write!(
+                f,
+                "InvalidSlippage: {}, expected percent such as 0.5 or bps such as 50bps",
+                s
+            ),
+            SlippageError::OutOfRange(s) => {
+                write!(f, "InvalidSlippage: {} is not between 0 and 100%", s)
+            }
+            SlippageError::Precision(pct) => {
+                write!(f, "InvalidSlippage: {}% is finer than 1 bps", pct)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SlippageError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slippage() {
+        assert_eq!("50bps".parse::<Slippage>().unwrap().bps(), 50);
+        assert_eq!("50 bps".parse::<Slippage>().unwrap().bps(), 50);
+        assert_eq!("0.5%".parse::<Slippage>().unwrap().bps(), 50);
+        assert_eq!("5".parse::<Slippage>().unwrap().bps(), 500);
+        assert_eq!("100".parse::<Slippage>().unwrap().bps(), 10_000);
+        assert_eq!(Slippage::default().to_string(), "5%");
+        assert_eq!(Slippage::from_bps(25).unwrap().to_string(), "0.25%");
+
+        assert!(matches!(
+            "101".parse::<Slippage>(),
+            Err(SlippageError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            "10001bps".parse::<Slippage>(),
+            Err(SlippageError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            "-1".parse::<Slippage>(),
+            Err(SlippageError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            "0.001".parse::<Slippage>(),
+            Err(SlippageError::Precision(_))
+        ));
+        assert!(matches!(
+            "abc".parse::<Slippage>(),
+            Err(SlippageError::Invalid(_))
+        ));
+
+        let slippage: Slippage = serde_json::from_str("20").unwrap();
+        assert_eq!(slippage.bps(), 2_000);
+        let slippage: Slippage = serde_json::from_str("\"75bps\"").unwrap();
+        assert_eq!(slippage.bps(), 75);
+        assert!(serde_json::from_str::<Slippage>("200").is_err());
+    }
+
+    #[test]
+    fn test_max_amount_within() {
+        let slippage = Slippage::from_bps(500).unwrap();
+        assert_eq!(slippage.max_amount_within(1_050_000_000), 1_000_000_000);
+        assert_eq!(slippage.max_amount(1_000_000_000), 1_050_000_000);
+
+        for bps in [0, 1, 50, 500, 3_333, MAX_BPS] {
+            let slippage = Slippage::from_bps(bps).unwrap();
+            for budget in [0, 1, 2, 999, 1_983_955_720, u64::MAX / 2] {
+                let amount = slippage.max_amount_within(budget);
+                assert!(slippage.max_amount(amount) <= budget);
+                assert!(slippage.max_amount(amount + 1) > budget);
+            }
+        }
+    }
+
+    #[test]
+    fn test_slippage_bounds() {
+        let slippage = Slippage::from_bps(100).unwrap();
+        // 1% of 999 is 9.99, min rounds down and max rounds up
+        assert_eq!(slippage.min_amount(999), 989);
+        assert_eq!(slippage.max_amount(999), 1_009);
+        assert_eq!(slippage.min_amount(1_000), 990);
+        assert_eq!(slippage.max_amount(1_000), 1_010);
+
+        let none = Slippage::from_bps(0).unwrap();
+        assert_eq!(none.min_amount(u64::MAX), u64::MAX);
+        assert_eq!(none.max_amount(u64::MAX), u64::MAX);
+
+        let all = Slippage::from_bps(10_000).unwrap();
+        assert_eq!(all.min_amount(1_000), 0);
+        assert_eq!(all.max_amount(1_000), 2_000);
+    }
+}
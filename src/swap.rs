@@ -1,13 +1,22 @@
+use std::env;
+
 use anyhow::Result;
 use clap::ValueEnum;
-use serde::Deserialize;
-use tracing::info;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use tracing::{debug, info};
 
 use crate::{
+    amount::Amount,
     api::AppState,
-    get_rpc_client,
-    pump::{self, get_pump_info},
-    raydium,
+    preflight,
+    priority_fee::PriorityFee,
+    pump, pump_amm,
+    quote::{self, Quote, ResolvedAmount},
+    raydium, raydium_clmm, raydium_cpmm,
+    router::{self, Route},
+    slippage::Slippage,
+    tx::{SendOptions, SendResult},
 };
 
 #[derive(ValueEnum, Debug, Clone, Deserialize)]
@@ -30,52 +39,401 @@ pub enum SwapInType {
     /// Quantity
     #[serde(rename = "qty")]
     Qty,
-    /// Percentage
+    /// Percentage, of the token balance for sells and of the spendable SOL for buys
     #[serde(rename = "pct")]
     Pct,
+    /// Exact output quantity, the input is capped by slippage
+    #[serde(rename = "exact_out")]
+    ExactOut,
+    /// USD value, only support buy, converted to SOL at the SOL price
+    #[serde(rename = "usd")]
+    Usd,
+}
+
+/// Where a swap is executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Venue {
+    /// pump.fun bonding curve
+    Pump,
+    /// PumpSwap, for graduated pump.fun tokens
+    PumpAmm,
+    /// Raydium AMM v4
+    RaydiumAmm,
+    RaydiumCpmm,
+    RaydiumClmm,
+}
+
+impl std::fmt::Display for Venue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Venue::Pump => "pump fun",
+            Venue::PumpAmm => "pump amm",
+            Venue::RaydiumAmm => "raydium",
+            Venue::RaydiumCpmm => "raydium cpmm",
+            Venue::RaydiumClmm => "raydium clmm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Signatures of a sent swap, with the quote it was routed on and the quotes it beat.
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapResult {
+    #[serde(flatten)]
+    pub sent: SendResult,
+    #[serde(flatten)]
+    pub route: Route,
+    /// SOL spent by a USD or percentage buy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<ResolvedAmount>,
+}
+
+/// Checks a swap must pass before it is sent, unset guards are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SwapGuards {
+    /// max price impact, in percent
+    pub max_price_impact_pct: Option<f64>,
+    /// min reserve of the quote side of the pool, in UI units of the quote mint
+    #[serde(alias = "min_sol_liquidity")]
+    pub min_quote_liquidity: Option<f64>,
+    /// max share of the pool output reserve a swap may take, in percent
+    pub max_reserve_pct: Option<f64>,
+}
+
+impl SwapGuards {
+    /// Global guards from MAX_PRICE_IMPACT_PCT, MIN_POOL_QUOTE_LIQUIDITY and MAX_POOL_RESERVE_PCT.
+    /// MIN_POOL_SOL_LIQUIDITY is still read when MIN_POOL_QUOTE_LIQUIDITY is unset.
+    pub fn from_env() -> Self {
+        let get = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<f64>().ok())
+        };
+        Self {
+            max_price_impact_pct: get("MAX_PRICE_IMPACT_PCT"),
+            min_quote_liquidity: get("MIN_POOL_QUOTE_LIQUIDITY")
+                .or_else(|| get("MIN_POOL_SOL_LIQUIDITY")),
+            max_reserve_pct: get("MAX_POOL_RESERVE_PCT"),
+        }
+    }
+
+    /// Per-request guards, falling back to `global` for the ones not set.
+    pub fn or(self, global: SwapGuards) -> Self {
+        Self {
+            max_price_impact_pct: self.max_price_impact_pct.or(global.max_price_impact_pct),
+            min_quote_liquidity: self.min_quote_liquidity.or(global.min_quote_liquidity),
+            max_reserve_pct: self.max_reserve_pct.or(global.max_reserve_pct),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &SwapGuards::default()
+    }
+
+    pub fn check(&self, quote: &Quote) -> Result<(), GuardError> {
+        if let Some(max) = self.max_price_impact_pct {
+            if quote.price_impact_pct > max {
+                return Err(GuardError::PriceImpact {
+                    price_impact_pct: quote.price_impact_pct,
+                    max,
+                });
+            }
+        }
+        if let Some(min) = self.min_quote_liquidity {
+            let liquidity = quote.quote_liquidity();
+            if liquidity < min {
+                return Err(GuardError::Liquidity {
+                    liquidity,
+                    min,
+                    quote_mint: quote.quote_mint.clone(),
+                });
+            }
+        }
+        if let Some(max) = self.max_reserve_pct {
+            let reserve_pct = quote.reserve_pct();
+            if reserve_pct > max {
+                return Err(GuardError::ReserveShare { reserve_pct, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardError {
+    PriceImpact {
+        price_impact_pct: f64,
+        max: f64,
+    },
+    Liquidity {
+        liquidity: f64,
+        min: f64,
+        quote_mint: String,
+    },
+    ReserveShare {
+        reserve_pct: f64,
+        max: f64,
+    },
+}
+
+impl std::fmt::Display for GuardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuardError::PriceImpact {
+                price_impact_pct,
+                max,
+            } => write!(
+                f,
+                "SwapGuard: price impact {:.2}% exceeds max {}%",
+                price_impact_pct, max
+            ),
+            GuardError::Liquidity {
+                liquidity,
+                min,
+                quote_mint,
+            } => {
+                let unit = if *quote_mint == spl_token::native_mint::ID.to_string() {
+                    "SOL"
+                } else {
+                    quote_mint.as_str()
+                };
+                write!(
+                    f,
+                    "SwapGuard: pool liquidity {:.4} {} is below min {} {}",
+                    liquidity, unit, min, unit
+                )
+            }
+            GuardError::ReserveShare { reserve_pct, max } => write!(
+                f,
+                "SwapGuard: swap takes {:.2}% of the pool reserve, max {}%",
+                reserve_pct, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GuardError {}
+
+/// No pool of a venue trades the mint, routing skips the venue on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotFoundPool(pub String);
+
+impl NotFoundPool {
+    /// Whether the error, or the error a context wraps, is a `NotFoundPool`.
+    pub fn is(err: &anyhow::Error) -> bool {
+        err.is::<NotFoundPool>()
+    }
+}
+
+impl std::fmt::Display for NotFoundPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NotFoundPool: {}", self.0)
+    }
+}
+
+impl std::error::Error for NotFoundPool {}
+
+/// Options every venue swap shares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapOptions {
+    pub slippage: Slippage,
+    pub send: SendOptions,
+}
+
+impl SwapOptions {
+    pub fn new(slippage: Slippage, use_jito: bool, priority_fee: PriorityFee) -> Self {
+        Self {
+            slippage,
+            send: SendOptions::new(use_jito, priority_fee),
+        }
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn swap(
     state: AppState,
     mint: &str,
-    amount_in: f64,
+    amount_in: Amount,
     swap_direction: SwapDirection,
     in_type: SwapInType,
-    slippage: u64,
-    use_jito: bool,
-) -> Result<Vec<String>> {
-    let client = get_rpc_client()?;
+    options: SwapOptions,
+    guards: SwapGuards,
+    quote_mint: Pubkey,
+) -> Result<SwapResult> {
+    let client = state.client.clone();
     let wallet = state.wallet;
+    // the tip checked against the balance is the one sent
+    let options = SwapOptions {
+        send: options.send.with_jito_tip().await?,
+        ..options
+    };
+    let SwapOptions { slippage, send } = options;
 
-    let pump_info_result = get_pump_info(client.clone(), mint).await;
-
-    println!("pump_info_result: {:#?}", pump_info_result);
-
-    match pump_info_result {
-        Ok(pump_info) => {
-            if !pump_info.complete {
-                // Pump token not completed, use original pump trading
-                info!("swap in pump fun");
-                let swapx = pump::Pump::new(client, wallet);
-                swapx
-                    .swap(mint, amount_in, swap_direction, in_type, slippage, use_jito)
-                    .await
-            } else {
-                // Pump token completed, use pump amm trading
-                // info!("swap in pump amm");
-                Err(anyhow::anyhow!(
-                    "Pump token {} is completed, not support swap in pump amm yet",
-                    mint
-                ))
-            }
+    let resolved = quote::resolve_buy_amount(
+        client.clone(),
+        wallet.clone(),
+        mint,
+        amount_in,
+        &swap_direction,
+        &in_type,
+        &quote_mint,
+        slippage,
+        &send,
+    )
+    .await?;
+    let (amount_in, in_type) = match &resolved {
+        Some(resolved) => (Amount::Raw(resolved.amount_in), SwapInType::Qty),
+        None => (amount_in, in_type),
+    };
+    let route = router::best_route(
+        client.clone(),
+        wallet.clone(),
+        mint,
+        amount_in,
+        swap_direction.clone(),
+        in_type.clone(),
+        slippage,
+        quote_mint,
+    )
+    .await?;
+    debug!("route: {:?}", route);
+    guards.check(&route.quote)?;
+    preflight::check_balance(
+        client.clone(),
+        wallet.clone(),
+        &route.quote,
+        slippage,
+        &send,
+    )
+    .await?;
+    let venue = route.quote.venue;
+    let pool_id = Some(route.quote.pool.clone());
+    info!("swap in {}", venue);
+    let sent = match venue {
+        Venue::Pump => {
+            let swapx = pump::Pump::new(client, wallet);
+            swapx
+                .swap(mint, amount_in, swap_direction, in_type, options)
+                .await?
+        }
+        Venue::PumpAmm => {
+            let mut swapx = pump_amm::PumpAmm::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .swap(mint, amount_in, swap_direction, in_type, options)
+                .await?
         }
-        Err(_err) => {
-            // Not a pump token or failed to get pump info, use raydium
-            info!("swap in raydium");
-            let swapx = raydium::Raydium::new(client, wallet);
+        Venue::RaydiumAmm => {
+            let mut swapx = raydium::Raydium::new(client, wallet);
             swapx
-                .swap(mint, amount_in, swap_direction, in_type, slippage, use_jito)
-                .await
+                .with_pool_id(pool_id)
+                .with_quote_mint(Some(quote_mint))
+                .swap(mint, amount_in, swap_direction, in_type, options)
+                .await?
         }
+        Venue::RaydiumCpmm => {
+            let mut swapx = raydium_cpmm::RaydiumCpmm::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .swap(mint, amount_in, swap_direction, in_type, options)
+                .await?
+        }
+        Venue::RaydiumClmm => {
+            let mut swapx = raydium_clmm::RaydiumClmm::new(client, wallet);
+            swapx
+                .with_pool_id(pool_id)
+                .swap(mint, amount_in, swap_direction, in_type, options)
+                .await?
+        }
+    };
+    Ok(SwapResult {
+        sent,
+        route,
+        resolved,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote() -> Quote {
+        let mut quote = Quote::new(
+            Venue::RaydiumAmm,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            6,
+            &SwapDirection::Buy,
+        );
+        quote.amount_in = 1_000_000_000;
+        quote.amount_out = 90_000_000;
+        quote.reserve_in = 10_000_000_000;
+        quote.reserve_out = 1_000_000_000;
+        quote.price_impact_pct = 9.09;
+        quote
+    }
+
+    #[test]
+    fn test_swap_guards() {
+        let quote = quote();
+        assert!(SwapGuards::default().is_empty());
+        assert!(SwapGuards::default().check(&quote).is_ok());
+
+        let guards = SwapGuards {
+            max_price_impact_pct: Some(5.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            guards.check(&quote),
+            Err(GuardError::PriceImpact { .. })
+        ));
+
+        // 10 SOL in the pool
+        let guards = SwapGuards {
+            min_quote_liquidity: Some(20.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            guards.check(&quote),
+            Err(GuardError::Liquidity { .. })
+        ));
+
+        // takes 9% of the token reserve
+        let guards = SwapGuards {
+            max_reserve_pct: Some(5.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            guards.check(&quote),
+            Err(GuardError::ReserveShare { .. })
+        ));
+        assert!(SwapGuards {
+            max_reserve_pct: Some(10.0),
+            ..Default::default()
+        }
+        .check(&quote)
+        .is_ok());
+    }
+
+    #[test]
+    fn test_swap_guards_or() {
+        let global = SwapGuards {
+            max_price_impact_pct: Some(5.0),
+            min_quote_liquidity: Some(10.0),
+            max_reserve_pct: None,
+        };
+        let guards = SwapGuards {
+            max_price_impact_pct: Some(20.0),
+            ..Default::default()
+        }
+        .or(global);
+        assert_eq!(guards.max_price_impact_pct, Some(20.0));
+        assert_eq!(guards.min_quote_liquidity, Some(10.0));
+        assert_eq!(guards.max_reserve_pct, None);
+
+        // requests using the old field name keep working
+        let guards: SwapGuards = serde_json::from_str(r#"{"min_sol_liquidity": 3}"#).unwrap();
+        assert_eq!(guards.min_quote_liquidity, Some(3.0));
     }
 }
@@ -3,10 +3,13 @@ use std::sync::Arc;
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use solana_account_decoder::UiAccountData;
-use solana_client::{rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
-use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey, signature::Keypair};
 use spl_token_2022::{
-    extension::StateWithExtensionsOwned,
+    extension::{
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensionsOwned,
+    },
     state::{Account, Mint},
 };
 use spl_token_client::{
@@ -23,13 +26,6 @@ pub struct TokenAccount {
     pub amount: String,
     pub ui_amount: f64,
 }
-#[derive(Debug, Serialize, Deserialize)]
-struct ParsedAccount {
-    program: String,
-    parsed: Parsed,
-    space: u64,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 struct Parsed {
     info: TokenInfo,
@@ -70,20 +66,27 @@ pub async fn token_account(
 }
 
 pub async fn token_accounts(client: &RpcClient, owner: &Pubkey) -> Result<TokenAccounts> {
-    token_accounts_filter(
-        client,
-        owner,
-        TokenAccountsFilter::ProgramId(spl_token::id()),
-    )
-    .await
+    let mut token_accounts = vec![];
+    for program_id in [spl_token::id(), spl_token_2022::id()] {
+        token_accounts.extend(
+            token_accounts_filter(client, owner, TokenAccountsFilter::ProgramId(program_id))
+                .await?,
+        );
+    }
+    Ok(token_accounts)
+}
+
+/// Whether `program_id` is the token program or Token-2022.
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == spl_token::ID || *program_id == spl_token_2022::ID
 }
+
 async fn token_accounts_filter(
     client: &RpcClient,
     owner: &Pubkey,
     filter: TokenAccountsFilter,
 ) -> Result<TokenAccounts> {
     let token_accounts = client
-        .get_inner_client()
         .get_token_accounts_by_owner(owner, filter)
         .await
         .expect("Failed to get token accounts");
@@ -119,7 +122,7 @@ pub async fn get_account_info(
     account: &Pubkey,
 ) -> TokenResult<StateWithExtensionsOwned<Account>> {
     let program_client = Arc::new(ProgramRpcClient::new(
-        client.get_inner_client().clone(),
+        client.clone(),
         ProgramRpcClientSendTransaction,
     ));
     let account = program_client
@@ -129,7 +132,7 @@ pub async fn get_account_info(
         .ok_or(TokenError::AccountNotFound)
         .inspect_err(|err| warn!("{} {}: mint {}", account, err, address))?;
 
-    if account.owner != spl_token::ID {
+    if !is_token_program(&account.owner) {
         return Err(TokenError::AccountInvalidOwner);
     }
     let account = StateWithExtensionsOwned::<Account>::unpack(account.data)?;
@@ -164,8 +167,28 @@ pub async fn get_mint_info(
     _keypair: Arc<Keypair>,
     address: &Pubkey,
 ) -> TokenResult<StateWithExtensionsOwned<Mint>> {
+    get_mint_info_with_program(client, address)
+        .await
+        .map(|(mint, _)| mint)
+}
+
+/// Token program owning the mint, token or Token-2022.
+pub async fn get_mint_program(client: Arc<RpcClient>, address: &Pubkey) -> TokenResult<Pubkey> {
+    if *address == spl_token::native_mint::ID {
+        return Ok(spl_token::ID);
+    }
+    get_mint_info_with_program(client, address)
+        .await
+        .map(|(_, program_id)| program_id)
+}
+
+/// The mint and the token program owning it.
+pub async fn get_mint_info_with_program(
+    client: Arc<RpcClient>,
+    address: &Pubkey,
+) -> TokenResult<(StateWithExtensionsOwned<Mint>, Pubkey)> {
     let program_client = Arc::new(ProgramRpcClient::new(
-        client.get_inner_client().clone(),
+        client.clone(),
         ProgramRpcClientSendTransaction,
     ));
     let account = program_client
@@ -175,9 +198,10 @@ pub async fn get_mint_info(
         .ok_or(TokenError::AccountNotFound)
         .inspect_err(|err| warn!("{} {}: mint {}", address, err, address))?;
 
-    if account.owner != spl_token::ID {
+    if !is_token_program(&account.owner) {
         return Err(TokenError::AccountInvalidOwner);
     }
+    let program_id = account.owner;
 
     let mint_result = StateWithExtensionsOwned::<Mint>::unpack(account.data).map_err(Into::into);
     let decimals: Option<u8> = None;
@@ -187,7 +211,73 @@ pub async fn get_mint_info(
         }
     }
 
-    mint_result
+    mint_result.map(|mint| (mint, program_id))
+}
+
+/// Size of an associated token account of the mint, with the extensions its Token-2022 mint
+/// requires and the immutable owner every Token-2022 associated account has.
+pub fn ata_len(mint: &StateWithExtensionsOwned<Mint>, program_id: &Pubkey) -> Result<usize> {
+    if *program_id != spl_token_2022::ID {
+        return Ok(Account::LEN);
+    }
+    let mut extension_types =
+        ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+    extension_types.push(ExtensionType::ImmutableOwner);
+    Ok(ExtensionType::try_calculate_account_len::<Account>(
+        &extension_types,
+    )?)
+}
+
+/// Token-2022 transfer fee of a mint in an epoch, no fee for mints without the extension.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MintTransferFee {
+    pub epoch: u64,
+    pub config: Option<TransferFeeConfig>,
+}
+
+impl MintTransferFee {
+    pub fn new(mint: &StateWithExtensionsOwned<Mint>, epoch: u64) -> Self {
+        Self {
+            epoch,
+            config: mint.get_extension::<TransferFeeConfig>().ok().copied(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fee(u64::MAX) == 0
+    }
+
+    /// Fee withheld from a transfer of `amount`.
+    pub fn fee(&self, amount: u64) -> u64 {
+        self.config
+            .and_then(|config| config.calculate_epoch_fee(self.epoch, amount))
+            .unwrap_or(0)
+    }
+
+    /// Fee to add on top of a transfer for `post_fee_amount` to arrive.
+    pub fn inverse_fee(&self, post_fee_amount: u64) -> u64 {
+        self.config
+            .and_then(|config| config.calculate_inverse_epoch_fee(self.epoch, post_fee_amount))
+            .unwrap_or(0)
+    }
+
+    /// What arrives of a transfer of `amount`.
+    pub fn post_fee_amount(&self, amount: u64) -> u64 {
+        amount.saturating_sub(self.fee(amount))
+    }
+}
+
+pub async fn get_transfer_fee(
+    client: Arc<RpcClient>,
+    keypair: Arc<Keypair>,
+    address: &Pubkey,
+) -> Result<MintTransferFee> {
+    let mint = get_mint_info(client.clone(), keypair, address).await?;
+    if mint.get_extension::<TransferFeeConfig>().is_err() {
+        return Ok(MintTransferFee::default());
+    }
+    let epoch = client.get_epoch_info().await?.epoch;
+    Ok(MintTransferFee::new(&mint, epoch))
 }
 
 // pub async fn get_mint_info(
@@ -210,6 +300,70 @@ pub async fn get_mint_info(
 
 #[cfg(test)]
 mod tests {
+    use spl_token_2022::extension::{transfer_fee::TransferFee, StateWithExtensionsMut};
+
+    use super::*;
+
+    #[test]
+    fn test_transfer_fee() {
+        assert!(MintTransferFee::default().is_empty());
+        assert_eq!(MintTransferFee::default().post_fee_amount(1_000), 1_000);
+
+        let transfer_fee = |epoch: u64, bps: u16, maximum_fee: u64| TransferFee {
+            epoch: epoch.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: bps.into(),
+        };
+        let config = TransferFeeConfig {
+            older_transfer_fee: transfer_fee(0, 100, u64::MAX),
+            newer_transfer_fee: transfer_fee(10, 250, 5_000),
+            ..Default::default()
+        };
+        // 1% before epoch 10
+        let fee = MintTransferFee {
+            epoch: 9,
+            config: Some(config),
+        };
+        assert_eq!(fee.fee(10_000), 100);
+        assert_eq!(fee.post_fee_amount(10_000), 9_900);
+        // 2.5% from epoch 10, capped at 5000
+        let fee = MintTransferFee {
+            epoch: 10,
+            config: Some(config),
+        };
+        assert_eq!(fee.fee(10_000), 250);
+        assert_eq!(fee.fee(1_000_000), 5_000);
+        assert!(!fee.is_empty());
+    }
+
+    #[test]
+    fn test_ata_len() {
+        let mut data = vec![
+            0;
+            ExtensionType::try_calculate_account_len::<Mint>(&[
+                ExtensionType::TransferFeeConfig
+            ])
+            .unwrap()
+        ];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        state.init_extension::<TransferFeeConfig>(true).unwrap();
+        state.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        let mint = StateWithExtensionsOwned::<Mint>::unpack(data).unwrap();
+
+        assert_eq!(ata_len(&mint, &spl_token::ID).unwrap(), Account::LEN);
+        // account type, then transfer fee amount and immutable owner
+        assert_eq!(
+            ata_len(&mint, &spl_token_2022::ID).unwrap(),
+            Account::LEN + 1 + (4 + 8) + 4
+        );
+    }
+
     #[cfg(feature = "slow_tests")]
     mod slow_tests {
         use crate::{get_rpc_client, token::token_account};
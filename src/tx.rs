@@ -1,24 +1,39 @@
 use std::{env, sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Result};
+use futures_util::{stream::FuturesUnordered, StreamExt};
 use jito_json_rpc_client::jsonrpc_client::rpc_client::RpcClient as JitoRpcClient;
-use solana_client::rpc_client::RpcClient;
+use serde::Serialize;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig},
+};
 use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
     instruction::Instruction,
+    message::{v0, VersionedMessage},
     signature::Keypair,
     signer::Signer,
     system_transaction,
-    transaction::{Transaction, VersionedTransaction},
+    transaction::VersionedTransaction,
 };
-use spl_token::ui_amount_to_amount;
+use spl_token::{amount_to_ui_amount, ui_amount_to_amount};
 
 use std::str::FromStr;
-use tokio::time::Instant;
-use tracing::{error, info};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, error, info, warn};
 
-use crate::jito::{self, get_tip_account, get_tip_value, wait_for_bundle_confirmation};
+use crate::{
+    endpoint_host, get_fanout_rpc_urls,
+    jito::{self, get_tip_account, get_tip_value, wait_for_bundle_confirmation},
+    lookup_table,
+    priority_fee::PriorityFee,
+};
 // prioritization fee = UNIT_PRICE * UNIT_LIMIT
-fn get_unit_price() -> u64 {
+pub(crate) fn get_unit_price() -> u64 {
     env::var("UNIT_PRICE")
         .ok()
         .and_then(|v| u64::from_str(&v).ok())
@@ -32,38 +47,505 @@ fn get_unit_limit() -> u32 {
         .unwrap_or(200_000)
 }
 
+/// Most compute units a transaction may use.
+pub const MAX_UNIT_LIMIT: u32 = 1_400_000;
+
+// size the unit limit to the units a simulation consumed, UNIT_LIMIT when it fails
+fn get_unit_limit_simulate() -> bool {
+    env::var("UNIT_LIMIT_SIMULATE").ok() == Some("true".to_string())
+}
+
+// margin over the simulated units, in percent
+fn get_unit_limit_margin_pct() -> u32 {
+    env::var("UNIT_LIMIT_MARGIN_PCT")
+        .ok()
+        .and_then(|v| u32::from_str(&v).ok())
+        .unwrap_or(10)
+}
+
+/// How a transaction is sent and what it pays to land.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SendOptions {
+    pub use_jito: bool,
+    pub priority_fee: PriorityFee,
+    /// margin over the simulated units, in percent
+    pub unit_limit_margin_pct: u32,
+    /// jito tip in lamports, fetched when the transaction is sent if unset
+    pub jito_tip: Option<u64>,
+}
+
+impl SendOptions {
+    /// Options with the unit limit margin from UNIT_LIMIT_MARGIN_PCT.
+    pub fn new(use_jito: bool, priority_fee: PriorityFee) -> Self {
+        Self {
+            use_jito,
+            priority_fee,
+            unit_limit_margin_pct: get_unit_limit_margin_pct(),
+            jito_tip: None,
+        }
+    }
+
+    /// Lamports tipped to jito, nothing when not using jito.
+    pub async fn jito_tip(&self) -> Result<u64> {
+        match (self.use_jito, self.jito_tip) {
+            (false, _) => Ok(0),
+            (true, Some(tip)) => Ok(tip),
+            (true, None) => get_tip_lamports().await,
+        }
+    }
+
+    /// Fetches the jito tip now, so the cost checked before sending is the tip paid.
+    pub async fn with_jito_tip(mut self) -> Result<Self> {
+        if self.use_jito {
+            self.jito_tip = Some(self.jito_tip().await?);
+        }
+        Ok(self)
+    }
+}
+
+/// Most compute units a swap is given, UNIT_LIMIT unless it is sized by simulation.
+fn max_unit_limit() -> u32 {
+    if get_unit_limit_simulate() {
+        MAX_UNIT_LIMIT
+    } else {
+        get_unit_limit()
+    }
+}
+
+/// Unit limit covering `units_consumed` with `margin_pct` percent to spare.
+pub fn unit_limit_with_margin(units_consumed: u64, margin_pct: u32) -> u32 {
+    let unit_limit = (units_consumed as u128 * (100 + margin_pct as u128)).div_ceil(100);
+    unit_limit.min(MAX_UNIT_LIMIT as u128) as u32
+}
+
+/// Fee of one signature, the base fee every transaction pays.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Most lamports a swap pays in transaction fees: the base fee of the swap, and of the tip
+/// transaction with jito, plus the priority fee when not using jito.
+pub fn max_fee_lamports(use_jito: bool, priority_fee: PriorityFee) -> u64 {
+    if use_jito {
+        return 2 * LAMPORTS_PER_SIGNATURE;
+    }
+    LAMPORTS_PER_SIGNATURE + priority_fee.max_lamports(max_unit_limit())
+}
+
+/// Jito tip in lamports, the upper limit is 0.1 SOL.
+pub async fn get_tip_lamports() -> Result<u64> {
+    let tip = get_tip_value().await?.min(0.1);
+    Ok(ui_amount_to_amount(tip, spl_token::native_mint::DECIMALS))
+}
+
+/// How often the signature status is polled while waiting for confirmation.
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// resend an unconfirmed transaction this often until its blockhash expires
+fn get_rebroadcast_interval() -> Duration {
+    let millis = env::var("TX_REBROADCAST_INTERVAL_MS")
+        .ok()
+        .and_then(|v| u64::from_str(&v).ok())
+        .unwrap_or(2_000);
+    Duration::from_millis(millis)
+}
+
+/// Final state of a transaction sent through the rpc.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    Confirmed,
+    /// landed with an on-chain error
+    Failed,
+    /// not landed before its blockhash expired
+    Expired,
+}
+
+/// How a transaction sent through the rpc ended.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Confirmation {
+    pub signature: String,
+    pub status: TxStatus,
+    /// slot the transaction landed in
+    pub slot: Option<u64>,
+    /// on-chain error of a failed transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub err: Option<String>,
+    /// from the first send to the final status
+    pub latency_ms: u64,
+    /// times the transaction was sent
+    pub sends: u32,
+    /// host of the endpoint that acknowledged the first fanned out send first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_ack: Option<String>,
+}
+
+impl Confirmation {
+    /// The confirmation if the transaction is confirmed, an error describing how it ended
+    /// otherwise.
+    pub fn into_result(self) -> Result<Self> {
+        match self.status {
+            TxStatus::Confirmed => Ok(self),
+            TxStatus::Failed => Err(anyhow!(
+                "TxFailed: {} failed in slot {}: {}",
+                self.signature,
+                self.slot.unwrap_or_default(),
+                self.err.unwrap_or_default()
+            )),
+            TxStatus::Expired => Err(anyhow!(
+                "TxExpired: {} was not confirmed before its blockhash expired, sent {} times in {} ms",
+                self.signature,
+                self.sends,
+                self.latency_ms
+            )),
+        }
+    }
+}
+
+/// Tokens a swap left in the wallet besides its output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Leftover {
+    pub mint: String,
+    /// raw units of the mint
+    pub amount: u64,
+}
+
+/// Signatures of sent transactions, with the confirmation of the rpc send path.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SendResult {
+    pub txs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<Confirmation>,
+    /// what the first leg of a two hop swap received above the input of the second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leftover: Option<Leftover>,
+}
+
+impl SendResult {
+    /// Whether the transaction landed, a jito bundle only returns once it has.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmation
+            .as_ref()
+            .is_none_or(|confirmation| confirmation.status == TxStatus::Confirmed)
+    }
+
+    /// The result if its transaction is confirmed, for callers that cannot go on after a
+    /// failed or expired send.
+    pub fn into_result(self) -> Result<Self> {
+        match self.confirmation {
+            Some(confirmation) => Ok(SendResult {
+                confirmation: Some(confirmation.into_result()?),
+                ..self
+            }),
+            None => Ok(self),
+        }
+    }
+}
+
+/// Sends the transaction to every url at once and returns the host of the first one to
+/// acknowledge its signature, the other sends finish in the background.
+pub async fn broadcast(
+    txn: &VersionedTransaction,
+    urls: &[String],
+    config: RpcSendTransactionConfig,
+) -> Result<String> {
+    let signature = txn.signatures[0];
+    let mut sends = urls
+        .iter()
+        .map(|url| {
+            let url = url.clone();
+            let txn = txn.clone();
+            tokio::spawn(async move {
+                let client = RpcClient::new(url.clone());
+                let result = client.send_transaction_with_config(&txn, config).await;
+                (url, result)
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+    let mut errors = Vec::new();
+    while let Some(sent) = sends.next().await {
+        match sent {
+            Ok((url, Ok(acked))) if acked == signature => return Ok(endpoint_host(&url)),
+            Ok((url, Ok(acked))) => {
+                errors.push(format!("{} acknowledged {}", endpoint_host(&url), acked))
+            }
+            Ok((url, Err(err))) => errors.push(format!("{}: {}", endpoint_host(&url), err)),
+            Err(err) => errors.push(err.to_string()),
+        }
+    }
+    Err(anyhow!(
+        "no endpoint acknowledged {}: {}",
+        signature,
+        errors.join("; ")
+    ))
+}
+
+/// Sends the transaction every TX_REBROADCAST_INTERVAL_MS until it is confirmed, fails on
+/// chain or `last_valid_block_height` passes. A transaction processed by then is awaited
+/// without resending until it is confirmed or dropped. With TX_FANOUT each send goes to
+/// every endpoint.
+pub async fn send_until_confirmed(
+    client: &RpcClient,
+    txn: &VersionedTransaction,
+    last_valid_block_height: u64,
+) -> Result<Confirmation> {
+    let signature = txn.signatures[0];
+    let interval = get_rebroadcast_interval();
+    let config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        // the rpc node does not retry, the loop does
+        max_retries: Some(0),
+        ..RpcSendTransactionConfig::default()
+    };
+    let fanout_urls = get_fanout_rpc_urls()?;
+    let progress_bar = jito::new_progress_bar();
+    let start_time = Instant::now();
+    let mut last_sent: Option<Instant> = None;
+    let mut sends = 0;
+    let mut first_ack: Option<String> = None;
+    let mut landed = false;
+    let mut expired = false;
+    let confirmation = |status: TxStatus,
+                        slot: Option<u64>,
+                        err: Option<String>,
+                        sends: u32,
+                        first_ack: Option<String>| {
+        progress_bar.finish_and_clear();
+        Confirmation {
+            signature: signature.to_string(),
+            status,
+            slot,
+            err,
+            latency_ms: start_time.elapsed().as_millis() as u64,
+            sends,
+            first_ack,
+        }
+    };
+
+    loop {
+        if !expired && last_sent.is_none_or(|sent| sent.elapsed() >= interval) {
+            if fanout_urls.is_empty() {
+                if let Err(err) = client.send_transaction_with_config(txn, config).await {
+                    warn!("failed to send {}: {}", signature, err);
+                }
+            } else {
+                match broadcast(txn, &fanout_urls, config).await {
+                    Ok(endpoint) if first_ack.is_none() => {
+                        info!("{} acknowledged first by {}", signature, endpoint);
+                        first_ack = Some(endpoint);
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("failed to broadcast {}: {}", signature, err),
+                }
+            }
+            sends += 1;
+            last_sent = Some(Instant::now());
+            progress_bar.set_message(format!("Confirming {}: sent {} times", signature, sends));
+        }
+        sleep(CONFIRM_POLL_INTERVAL).await;
+
+        match client.get_signature_statuses(&[signature]).await {
+            Ok(response) => {
+                match response.value.into_iter().next().flatten() {
+                    Some(status) => {
+                        if let Some(err) = status.err {
+                            return Ok(confirmation(
+                                TxStatus::Failed,
+                                Some(status.slot),
+                                Some(err.to_string()),
+                                sends,
+                                first_ack,
+                            ));
+                        }
+                        if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                            info!("confirmed {} in slot {}", signature, status.slot);
+                            return Ok(confirmation(
+                                TxStatus::Confirmed,
+                                Some(status.slot),
+                                None,
+                                sends,
+                                first_ack,
+                            ));
+                        }
+                        landed = true;
+                        progress_bar.set_message(format!(
+                            "Confirming {}: processed in slot {}",
+                            signature, status.slot
+                        ));
+                    }
+                    // processed once but dropped since, it can no longer land
+                    None if expired => {
+                        warn!("{} dropped after its blockhash expired", signature);
+                        return Ok(confirmation(
+                            TxStatus::Expired,
+                            None,
+                            None,
+                            sends,
+                            first_ack,
+                        ));
+                    }
+                    None => landed = false,
+                }
+            }
+            Err(err) => warn!("failed to get status of {}: {}", signature, err),
+        }
+
+        if expired {
+            continue;
+        }
+        match client.get_block_height().await {
+            Ok(block_height) if block_height > last_valid_block_height && landed => {
+                info!(
+                    "{} blockhash expired at block height {}, waiting for confirmation",
+                    signature, block_height
+                );
+                expired = true;
+            }
+            Ok(block_height) if block_height > last_valid_block_height => {
+                warn!(
+                    "{} expired at block height {}, sent {} times",
+                    signature, block_height, sends
+                );
+                return Ok(confirmation(
+                    TxStatus::Expired,
+                    None,
+                    None,
+                    sends,
+                    first_ack,
+                ));
+            }
+            Ok(_) => {}
+            Err(err) => warn!("failed to get block height: {}", err),
+        }
+    }
+}
+
+/// Compiles the instructions into a v0 transaction signed by the payer.
+fn compile(
+    keypair: &Keypair,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let message = v0::Message::try_compile(
+        &keypair.pubkey(),
+        instructions,
+        lookup_tables,
+        recent_blockhash,
+    )?;
+    Ok(VersionedTransaction::try_new(
+        VersionedMessage::V0(message),
+        &[keypair],
+    )?)
+}
+
+/// Units the instructions consume in a simulation run with the max unit limit and
+/// `unit_price`, the compute budget instructions of the sent transaction, none when the
+/// simulation fails.
+async fn simulate_units_consumed(
+    client: &RpcClient,
+    keypair: &Keypair,
+    instructions: &[Instruction],
+    unit_price: u64,
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Option<u64> {
+    let mut instructions = instructions.to_vec();
+    instructions.insert(
+        0,
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_UNIT_LIMIT),
+    );
+    instructions.insert(
+        1,
+        ComputeBudgetInstruction::set_compute_unit_price(unit_price),
+    );
+    let txn = compile(keypair, &instructions, lookup_tables, recent_blockhash)
+        .inspect_err(|err| warn!("failed to compile simulation: {}", err))
+        .ok()?;
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = client
+        .simulate_transaction_with_config(&txn, config)
+        .await
+        .inspect_err(|err| warn!("failed to simulate: {}", err))
+        .ok()?;
+    if let Some(err) = result.value.err {
+        warn!("simulation failed: {}", err);
+        return None;
+    }
+    result.value.units_consumed
+}
+
 pub async fn new_signed_and_send(
     client: &RpcClient,
     keypair: &Keypair,
     mut instructions: Vec<Instruction>,
-    use_jito: bool,
-) -> Result<Vec<String>> {
-    let unit_limit = get_unit_limit();
-    let unit_price = get_unit_price();
+    options: SendOptions,
+) -> Result<SendResult> {
+    let SendOptions {
+        use_jito,
+        priority_fee,
+        unit_limit_margin_pct,
+        ..
+    } = options;
+    let (recent_blockhash, last_valid_block_height) = client
+        .get_latest_blockhash_with_commitment(client.commitment())
+        .await?;
+    let lookup_tables =
+        lookup_table::get_lookup_tables(client, &lookup_table::lookup_tables_from_env()?).await?;
     // If not using Jito, manually set the compute unit price and limit
     if !use_jito {
-        let modify_compute_units =
-            solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(
-                unit_limit,
-            );
-        let add_priority_fee =
-            solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_price(
+        let mut unit_limit = get_unit_limit();
+        let mut unit_price = priority_fee
+            .unit_price(client, &instructions, unit_limit)
+            .await?;
+        if get_unit_limit_simulate() {
+            match simulate_units_consumed(
+                client,
+                keypair,
+                &instructions,
                 unit_price,
-            );
+                &lookup_tables,
+                recent_blockhash,
+            )
+            .await
+            {
+                Some(units_consumed) => {
+                    let simulated_limit =
+                        unit_limit_with_margin(units_consumed, unit_limit_margin_pct);
+                    info!(
+                        "units consumed: {}, unit limit: {}",
+                        units_consumed, simulated_limit
+                    );
+                    // a budgeted price depends on the limit it is spread over
+                    if simulated_limit != unit_limit {
+                        unit_limit = simulated_limit;
+                        unit_price = priority_fee
+                            .unit_price(client, &instructions, unit_limit)
+                            .await?;
+                    }
+                }
+                None => warn!("unit limit falls back to {}", unit_limit),
+            }
+        }
+        let modify_compute_units = ComputeBudgetInstruction::set_compute_unit_limit(unit_limit);
+        let add_priority_fee = ComputeBudgetInstruction::set_compute_unit_price(unit_price);
         instructions.insert(0, modify_compute_units);
         instructions.insert(1, add_priority_fee);
     }
     // send init tx
-    let recent_blockhash = client.get_latest_blockhash()?;
-    let txn = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&keypair.pubkey()),
-        &vec![&*keypair],
-        recent_blockhash,
+    let txn = compile(keypair, &instructions, &lookup_tables, recent_blockhash)?;
+    debug!(
+        "v0 message: {} static accounts, {} lookup tables",
+        txn.message.static_account_keys().len(),
+        txn.message
+            .address_table_lookups()
+            .map_or(0, |lookups| lookups.len())
     );
 
     if env::var("TX_SIMULATE").ok() == Some("true".to_string()) {
-        let simulate_result = client.simulate_transaction(&txn)?;
+        let simulate_result = client.simulate_transaction(&txn).await?;
         if let Some(logs) = simulate_result.value.logs {
             for log in logs {
                 info!("{}", log);
@@ -71,41 +553,41 @@ pub async fn new_signed_and_send(
         }
         return match simulate_result.value.err {
             Some(err) => Err(anyhow!("{}", err)),
-            None => Ok(vec![]),
+            None => Ok(SendResult::default()),
         };
     }
 
     let start_time = Instant::now();
-    let mut txs = vec![];
+    let mut result = SendResult::default();
     if use_jito {
         // jito
         let tip_account = get_tip_account().await?;
-        // jito tip, the upper limit is 0.1
-        let mut tip = get_tip_value().await?;
-        tip = tip.min(0.1);
-        let tip_lamports = ui_amount_to_amount(tip, spl_token::native_mint::DECIMALS);
+        let tip_lamports = options.jito_tip().await?;
         info!(
             "tip account: {}, tip(sol): {}, lamports: {}",
-            tip_account, tip, tip_lamports
+            tip_account,
+            amount_to_ui_amount(tip_lamports, spl_token::native_mint::DECIMALS),
+            tip_lamports
         );
 
         let jito_client = Arc::new(JitoRpcClient::new(format!(
             "{}/api/v1/bundles",
-            jito::BLOCK_ENGINE_URL.to_string()
+            *jito::BLOCK_ENGINE_URL
         )));
         // tip tx
-        let mut bundle: Vec<VersionedTransaction> = vec![];
-        bundle.push(VersionedTransaction::from(txn));
-        bundle.push(VersionedTransaction::from(system_transaction::transfer(
-            &keypair,
-            &tip_account,
-            tip_lamports,
-            recent_blockhash,
-        )));
+        let bundle = vec![
+            txn,
+            VersionedTransaction::from(system_transaction::transfer(
+                keypair,
+                &tip_account,
+                tip_lamports,
+                recent_blockhash,
+            )),
+        ];
         let bundle_id = jito_client.send_bundle(&bundle).await?;
         info!("bundle_id: {}", bundle_id);
 
-        txs = wait_for_bundle_confirmation(
+        result.txs = wait_for_bundle_confirmation(
             move |id: String| {
                 let client = Arc::clone(&jito_client);
                 async move {
@@ -122,11 +604,112 @@ pub async fn new_signed_and_send(
         )
         .await?;
     } else {
-        let sig = common::rpc::send_txn(&client, &txn, true)?;
-        info!("signature: {:?}", sig);
-        txs.push(sig.to_string());
+        info!("signature: {:?}", txn.signatures[0]);
+        let confirmation = send_until_confirmed(client, &txn, last_valid_block_height).await?;
+        match confirmation.status {
+            TxStatus::Confirmed => {}
+            TxStatus::Failed => warn!(
+                "{} failed in slot {}: {}",
+                confirmation.signature,
+                confirmation.slot.unwrap_or_default(),
+                confirmation.err.as_deref().unwrap_or_default()
+            ),
+            TxStatus::Expired => warn!(
+                "{} was not confirmed before its blockhash expired",
+                confirmation.signature
+            ),
+        }
+        result.txs.push(confirmation.signature.clone());
+        result.confirmation = Some(confirmation);
     }
 
     info!("tx elapsed: {:?}", start_time.elapsed());
-    Ok(txs)
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_limit_with_margin() {
+        assert_eq!(unit_limit_with_margin(60_000, 10), 66_000);
+        // rounded up so the margin is never short
+        assert_eq!(unit_limit_with_margin(33_333, 10), 36_667);
+        assert_eq!(unit_limit_with_margin(50_000, 0), 50_000);
+        assert_eq!(unit_limit_with_margin(1_300_000, 20), MAX_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_confirmation_result() {
+        let confirmation = Confirmation {
+            signature: "sig".to_string(),
+            status: TxStatus::Confirmed,
+            slot: Some(42),
+            err: None,
+            latency_ms: 1_200,
+            sends: 1,
+            first_ack: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&confirmation).unwrap(),
+            serde_json::json!({
+                "signature": "sig",
+                "status": "confirmed",
+                "slot": 42,
+                "latency_ms": 1_200,
+                "sends": 1
+            })
+        );
+        assert!(confirmation.clone().into_result().is_ok());
+
+        let failed = Confirmation {
+            status: TxStatus::Failed,
+            err: Some("custom program error: 0x1".to_string()),
+            ..confirmation.clone()
+        };
+        // a failed send is still a result, with the status and the on-chain error
+        let sent = SendResult {
+            txs: vec!["sig".to_string()],
+            confirmation: Some(failed.clone()),
+            ..SendResult::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&sent).unwrap(),
+            serde_json::json!({
+                "txs": ["sig"],
+                "confirmation": {
+                    "signature": "sig",
+                    "status": "failed",
+                    "slot": 42,
+                    "err": "custom program error: 0x1",
+                    "latency_ms": 1_200,
+                    "sends": 1
+                }
+            })
+        );
+        assert!(!sent.is_confirmed());
+        assert_eq!(
+            sent.into_result().unwrap_err().to_string(),
+            "TxFailed: sig failed in slot 42: custom program error: 0x1"
+        );
+        let expired = Confirmation {
+            status: TxStatus::Expired,
+            slot: None,
+            sends: 3,
+            ..confirmation
+        };
+        assert_eq!(
+            serde_json::to_value(&expired).unwrap()["status"],
+            serde_json::json!("expired")
+        );
+        assert!(expired
+            .into_result()
+            .unwrap_err()
+            .to_string()
+            .starts_with("TxExpired: sig"));
+        // jito bundles carry no confirmation
+        assert!(SendResult::default().is_confirmed());
+        assert!(SendResult::default().into_result().is_ok());
+    }
 }